@@ -1,13 +1,27 @@
+mod query;
+mod stats;
+
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::env;
 
+use query::Expr;
+use stats::ColourEstimate;
+
+const DEFAULT_BAG: &str = "red=12,green=13,blue=14";
+const DEFAULT_CONFIDENCE: f64 = 0.95;
+
+// The cubes shown in a single handful, by colour
+#[derive(Debug, PartialEq)]
+struct Draw {
+    cubes: HashMap<String, usize>,
+}
+
 #[derive(Debug, PartialEq)]
 struct Game {
     id: usize,
-    max_blue: usize,
-    max_red: usize,
-    max_green: usize,
+    draws: Vec<Draw>,
 }
 
 #[derive(Debug)]
@@ -15,66 +29,115 @@ struct Games {
     games: Vec<Game>,
 }
 
+impl Draw {
+    fn new(cubes: HashMap<String, usize>) -> Draw {
+        Draw { cubes }
+    }
+
+    // Parse a handful such as "3 blue, 4 red"
+    fn from_hand(hand: &str) -> Draw {
+        let mut cubes: HashMap<String, usize> = HashMap::new();
+
+        for colour in hand.split(',') {
+            let colour = colour.trim();
+            let mut parts = colour.split(' ');
+            let count = parts.next().expect("Failed to get count").parse::<usize>().expect("Failed to parse count");
+            let colour = parts.next().expect("Failed to get colour").trim();
+            *cubes.entry(colour.to_string()).or_default() += count;
+        }
+
+        Draw::new(cubes)
+    }
+
+    fn total_cubes(&self) -> usize {
+        self.cubes.values().sum()
+    }
+}
+
 impl Game {
-    fn new(id: usize, max_blue: usize, max_red: usize, max_green: usize) -> Game {
+    fn new(id: usize, draws: Vec<Draw>) -> Game {
         Game {
             id,
-            max_blue,
-            max_red,
-            max_green,
+            draws,
         }
     }
 
     fn from_line(line: &str) -> Game {
         // Extract id
-        let before_id = line.find(" ").unwrap_or(0);
-        let after_id = line.find(":").unwrap_or(line.len());
+        let before_id = line.find(' ').unwrap_or(0);
+        let after_id = line.find(':').unwrap_or(line.len());
 
         let id_str = line[before_id..after_id].trim();
 
         let id = id_str.parse().expect("Failed to parse id");
 
         // Extract hands
-        let hands = line[after_id+1..].trim().split(";");
-
-        let mut max_blue = 0;
-        let mut max_red = 0;
-        let mut max_green = 0;
-
-        for hand in hands {
-            let colours = hand.split(",");
-            for colour in colours {
-                let colour = colour.trim();
-                let mut parts = colour.split(" ");
-                let count = parts.next().expect("Failed to get count").parse::<usize>().expect("Failed to parse count");
-                let colour = parts.next().expect("Failed to get colour").trim();
-                match colour {
-                    "blue" => if count > max_blue { max_blue = count },
-                    "red" => if count > max_red { max_red = count },
-                    "green" => if count > max_green { max_green = count },
-                    _ => panic!("Unknown colour"),
-                }
+        let draws = line[after_id+1..].trim().split(';')
+            .map(Draw::from_hand)
+            .collect();
+
+        Game::new(id, draws)
+    }
+
+    // The smallest bag that could have produced every draw: the maximum of each colour
+    fn min_bag(&self) -> HashMap<String, usize> {
+        let mut min_bag: HashMap<String, usize> = HashMap::new();
+
+        for draw in &self.draws {
+            for (colour, count) in &draw.cubes {
+                let max = min_bag.entry(colour.clone()).or_default();
+                if count > max { *max = *count }
             }
         }
 
-        Game::new(id, max_blue, max_red, max_green)
+        min_bag
     }
 
-    fn check_colours(&self, colours: &Vec<(&str, usize)>) -> bool {
-        for (colour, count) in colours {
-            match colour {
-                &"blue" => if &self.max_blue > count  { return false },
-                &"red" => if &self.max_red > count  { return false },
-                &"green" => if &self.max_green > count { return false },
-                _ => panic!("Unknown colour"),
+    // Every cube shown across all draws, summed by colour
+    fn cubes_seen(&self) -> HashMap<String, usize> {
+        let mut seen: HashMap<String, usize> = HashMap::new();
+
+        for draw in &self.draws {
+            for (colour, count) in &draw.cubes {
+                *seen.entry(colour.clone()).or_default() += count;
             }
         }
 
-        true
+        seen
+    }
+
+    fn total_cubes(&self) -> usize {
+        self.draws.iter().map(|draw| draw.total_cubes()).sum()
+    }
+
+    // The colour shown most often across all draws, ties broken alphabetically
+    fn most_frequent_colour(&self) -> Option<(String, usize)> {
+        self.cubes_seen().into_iter()
+            .max_by(|(colour_a, count_a), (colour_b, count_b)| count_a.cmp(count_b).then(colour_b.cmp(colour_a)))
+    }
+
+    // Estimate the bag contents from the draws, assuming the bag holds `bag_size`
+    // cubes. Every cube shown counts as a separate draw with replacement, even
+    // though the cubes of one handful come out together.
+    fn estimate_bag(&self, bag_size: usize, confidence: f64) -> Result<Vec<ColourEstimate>, String> {
+        let min_bag = self.min_bag();
+        stats::estimate_bag(&self.cubes_seen(), &min_bag, bag_size, confidence)
+    }
+
+    // A game is possible if every colour it showed fits in the bag; colours
+    // missing from the bag count as zero cubes.
+    fn check_colours(&self, bag: &HashMap<String, usize>) -> bool {
+        self.min_bag().iter()
+            .all(|(colour, max)| max <= bag.get(colour).unwrap_or(&0))
     }
 
-    fn get_game_power(&self) -> usize {
-        self.max_blue * self.max_red * self.max_green
+    // Product of the minimum cubes of each colour in the bag; a bag colour
+    // never drawn counts as zero, as in the puzzle
+    fn get_game_power(&self, bag: &HashMap<String, usize>) -> usize {
+        let min_bag = self.min_bag();
+        bag.keys()
+            .map(|colour| min_bag.get(colour).unwrap_or(&0))
+            .product()
     }
 }
 
@@ -103,94 +166,391 @@ impl Games {
         self.games.push(game);
     }
 
-    fn check_colours(&self, colours: &Vec<(&str, usize)>) -> usize {
-        match self.games.iter()
-            .filter(|game| game.check_colours(colours))
+    fn check_colours(&self, bag: &HashMap<String, usize>) -> usize {
+        self.games.iter()
+            .filter(|game| game.check_colours(bag))
             .map(|game| game.id)
-            .reduce(|a, b| a + b) {
-            Some(id) => id,
-            None => 0,
-            }
+            .sum()
     }
 
-    fn get_total_power(&self) -> usize {
+    fn query(&self, expr: &Expr, bag: &HashMap<String, usize>) -> Vec<&Game> {
         self.games.iter()
-            .map(|game| game.get_game_power())
-            .reduce(|a, b| a + b)
-            .unwrap_or(0)
+            .filter(|game| expr.matches(game, bag))
+            .collect()
+    }
+
+    fn get_total_power(&self, bag: &HashMap<String, usize>) -> usize {
+        self.games.iter()
+            .map(|game| game.get_game_power(bag))
+            .sum()
+    }
+
+    // Every colour in the bag or drawn in any game
+    fn colours(&self, bag: &HashMap<String, usize>) -> Vec<String> {
+        let mut colours: Vec<String> = bag.keys().cloned().collect();
+        for game in &self.games {
+            colours.extend(game.min_bag().into_keys());
+        }
+        colours.sort();
+        colours.dedup();
+        colours
+    }
+
+    // Estimate a bag shared by every game, pooling all of their draws
+    fn estimate_bag(&self, bag_size: usize, confidence: f64) -> Result<Vec<ColourEstimate>, String> {
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        let mut min_bag: HashMap<String, usize> = HashMap::new();
+
+        for game in &self.games {
+            for (colour, count) in game.cubes_seen() {
+                *seen.entry(colour).or_default() += count;
+            }
+            for (colour, count) in game.min_bag() {
+                let max = min_bag.entry(colour).or_default();
+                if count > *max { *max = count }
+            }
+        }
+
+        stats::estimate_bag(&seen, &min_bag, bag_size, confidence)
+    }
+}
+
+// Parse a bag definition such as "red=12,green=13,blue=14"
+fn parse_bag(definition: &str) -> Result<HashMap<String, usize>, String> {
+    let mut bag = HashMap::new();
+
+    for entry in definition.split(',') {
+        let (colour, count) = entry.split_once('=')
+            .ok_or(format!("Bag entry '{}' should look like colour=count", entry))?;
+        let colour = colour.trim();
+        if colour.is_empty() {
+            return Err(format!("Bag entry '{}' has no colour", entry));
+        }
+        let count = count.trim().parse::<usize>()
+            .map_err(|_| format!("Bag entry '{}' has an invalid count", entry))?;
+        if bag.insert(colour.to_string(), count).is_some() {
+            return Err(format!("Colour '{}' appears more than once in the bag", colour));
+        }
+    }
+
+    Ok(bag)
+}
+
+// Format a colour map as "blue=6 green=2 red=4"
+fn format_colours(colours: &HashMap<String, usize>) -> String {
+    let mut colours: Vec<_> = colours.iter().collect();
+    colours.sort();
+    colours.iter()
+        .map(|(colour, count)| format!("{}={}", colour, count))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn print_stats(games: &Games, bag: &HashMap<String, usize>, bag_size: Option<usize>, confidence: f64) {
+    for game in &games.games {
+        let most_frequent = match game.most_frequent_colour() {
+            Some((colour, count)) => format!("{} ({})", colour, count),
+            None => "none".to_string(),
+        };
+        println!(
+            "Game {}: {} draws, {} cubes seen, most frequent {}, minimum bag {} (power {})",
+            game.id, game.draws.len(), game.total_cubes(), most_frequent,
+            format_colours(&game.min_bag()), game.get_game_power(bag)
+        );
+        // Without a bag size the only consistent estimate is the minimum bag
+        // itself, so there is nothing more to say
+        if let Some(bag_size) = bag_size {
+            match game.estimate_bag(bag_size, confidence) {
+                Ok(estimates) => estimates.iter().for_each(|estimate| println!("  {}", estimate)),
+                Err(e) => println!("  Cannot estimate bag: {}", e),
+            }
+        }
+    }
+
+    let Some(bag_size) = bag_size else {
+        println!("Pass --bag-size to estimate the bag contents");
+        return;
+    };
+    println!("All games, assuming one shared bag:");
+    match games.estimate_bag(bag_size, confidence) {
+        Ok(estimates) => estimates.iter().for_each(|estimate| println!("  {}", estimate)),
+        Err(e) => println!("  Cannot estimate bag: {}", e),
+    }
+}
+
+// What `query` prints about the matching games
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Output {
+    List,
+    Count,
+    IdSum,
+    PowerSum,
+}
+
+impl TryFrom<&str> for Output {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "list" => Ok(Output::List),
+            "count" => Ok(Output::Count),
+            "id-sum" => Ok(Output::IdSum),
+            "power-sum" => Ok(Output::PowerSum),
+            _ => Err(format!("Unknown output '{}', expected list, count, id-sum or power-sum", value)),
+        }
+    }
+}
+
+fn print_query(games: &Games, bag: &HashMap<String, usize>, query: &str, output: Output) {
+    let expr = match Expr::parse(query, &games.colours(bag)) {
+        Ok(expr) => expr,
+        Err(e) => {
+            eprintln!("Invalid query: {}", e);
+            eprintln!("  {}", query);
+            eprintln!("  {}^", " ".repeat(e.position));
+            std::process::exit(1);
+        },
+    };
+    let matching = games.query(&expr, bag);
+
+    match output {
+        Output::List => for game in matching {
+            println!("Game {}: {} draws, minimum bag {} (power {})",
+                game.id, game.draws.len(), format_colours(&game.min_bag()), game.get_game_power(bag));
+        },
+        Output::Count => println!("Matching games: {}", matching.len()),
+        Output::IdSum => println!("Sum of matching IDs: {}", matching.iter().map(|game| game.id).sum::<usize>()),
+        Output::PowerSum => println!("Total matching power: {}", matching.iter().map(|game| game.get_game_power(bag)).sum::<usize>()),
     }
 }
 
+enum Command {
+    Check,
+    Stats,
+    Query(String),
+}
+
 fn main() {
-    // Get file name from command line
+    // Get optional subcommand, file name and options from command line
     let args: Vec<String> = env::args().collect();
-    let filename = args.get(1).expect("Please provide a filename");
+    let mut filename = None;
+    let mut bag_definition = DEFAULT_BAG.to_string();
+    let mut bag_size = None;
+    let mut confidence = DEFAULT_CONFIDENCE;
+    let mut output = Output::List;
+
+    let mut args = args.iter().skip(1).peekable();
+    let command = match args.peek().map(|arg| arg.as_str()) {
+        Some("stats") => {
+            args.next();
+            Command::Stats
+        },
+        Some("query") => {
+            args.next();
+            Command::Query(args.next().expect("Please provide a query, e.g. query \"red <= 12\"").clone())
+        },
+        _ => Command::Check,
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bag" => bag_definition = args.next().expect("Please provide a bag after --bag").clone(),
+            "--bag-size" => bag_size = Some(args.next().expect("Please provide a size after --bag-size")
+                .parse().expect("Failed to parse bag size")),
+            "--confidence" => confidence = args.next().expect("Please provide a level after --confidence")
+                .parse().expect("Failed to parse confidence"),
+            "--output" => {
+                let name = args.next().expect("Please provide an output after --output");
+                output = Output::try_from(name.as_str()).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                });
+            },
+            _ => filename = Some(arg),
+        }
+    }
+    let filename = filename.expect("Please provide a filename");
 
+    // Every command needs the bag: its colours are the ones power multiplies
+    let bag = parse_bag(&bag_definition).unwrap_or_else(|e| panic!("Failed to parse bag: {}", e));
     let games = Games::from_file(filename);
-    let result = games.check_colours(&vec![("red", 12), ("green", 13), ("blue", 14)]);
-    println!("Result is: {}", result);
 
-    let power = games.get_total_power();
-    println!("Total game power is: {}", power);
+    match command {
+        Command::Stats => print_stats(&games, &bag, bag_size, confidence),
+        Command::Query(query) => print_query(&games, &bag, &query, output),
+        Command::Check => {
+            let result = games.check_colours(&bag);
+            println!("Result is: {}", result);
+
+            let power = games.get_total_power(&bag);
+            println!("Total game power is: {}", power);
+        },
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    pub fn colours(counts: &[(&str, usize)]) -> HashMap<String, usize> {
+        counts.iter()
+            .map(|(colour, count)| (colour.to_string(), *count))
+            .collect()
+    }
+
+    fn game(id: usize, draws: &[&[(&str, usize)]]) -> Game {
+        Game::new(id, draws.iter().map(|cubes| Draw::new(colours(cubes))).collect())
+    }
+
     #[test]
     fn test_games_check_colours() {
-        let game1 = Game::new(1, 3, 4, 2);
-        let game2 = Game::new(2, 2, 3, 1);
-        let game3 = Game::new(3, 1, 2, 3);
+        let game1 = game(1, &[&[("blue", 3), ("red", 4), ("green", 2)]]);
+        let game2 = game(2, &[&[("blue", 2), ("red", 3), ("green", 1)]]);
+        let game3 = game(3, &[&[("blue", 1), ("red", 2), ("green", 3)]]);
 
         let games = Games {
             games: vec![game1, game2, game3],
         };
 
-        let colours1 = vec![("blue", 2), ("red", 3), ("green", 1)];
+        let colours1 = colours(&[("blue", 2), ("red", 3), ("green", 1)]);
         assert_eq!(games.check_colours(&colours1), 2);
 
-        let colours2 = vec![("blue", 1), ("red", 1), ("green", 1)];
+        let colours2 = colours(&[("blue", 1), ("red", 1), ("green", 1)]);
         assert_eq!(games.check_colours(&colours2), 0);
 
-        let colours3 = vec![("blue", 4), ("red", 2), ("green", 3)];
+        let colours3 = colours(&[("blue", 4), ("red", 2), ("green", 3)]);
         assert_eq!(games.check_colours(&colours3), 3);
     }
 
+    #[test]
+    fn test_game_check_other_colours() {
+        let game = Game::from_line("Game 7: 2 yellow, 1 purple; 4 yellow");
+
+        assert!(game.check_colours(&colours(&[("yellow", 4), ("purple", 1)])));
+        assert!(!game.check_colours(&colours(&[("yellow", 3), ("purple", 1)])));
+        assert!(!game.check_colours(&colours(&[("yellow", 4)])));
+
+        // Power multiplies the colours of the bag, whatever they are
+        assert_eq!(game.get_game_power(&colours(&[("yellow", 4), ("purple", 1)])), 4);
+        assert_eq!(game.get_game_power(&colours(&[("yellow", 4), ("purple", 1), ("orange", 2)])), 0);
+        assert_eq!(game.get_game_power(&colours(&[("yellow", 4)])), 4);
+        assert_eq!(game.get_game_power(&parse_bag(DEFAULT_BAG).unwrap()), 0);
+    }
+
+    #[test]
+    fn test_games_query() {
+        let games = Games {
+            games: vec![
+                game(1, &[&[("blue", 3), ("red", 4)], &[("green", 2)]]),
+                game(2, &[&[("blue", 20), ("red", 1)]]),
+                game(3, &[&[("red", 13), ("green", 6), ("blue", 1)]]),
+            ],
+        };
+
+        let bag = parse_bag(DEFAULT_BAG).unwrap();
+        assert_eq!(games.colours(&bag), vec!["blue", "green", "red"]);
+        assert_eq!(games.colours(&colours(&[("yellow", 1)])), vec!["blue", "green", "red", "yellow"]);
+
+        let expr = Expr::parse("red <= 12 and (green > 1 or blue == 0)", &games.colours(&bag)).unwrap();
+        let ids: Vec<_> = games.query(&expr, &bag).iter().map(|game| game.id).collect();
+        assert_eq!(ids, vec![1]);
+
+        let expr = Expr::parse("draws >= 2 or power > 70", &games.colours(&bag)).unwrap();
+        let ids: Vec<_> = games.query(&expr, &bag).iter().map(|game| game.id).collect();
+        assert_eq!(ids, vec![1, 3]);
+    }
+
     #[test]
     fn test_games_total_power() {
-        let game1 = Game::new(1, 6, 4, 2);
-        let game2 = Game::new(2, 4, 1, 3);
+        let game1 = game(1, &[&[("blue", 6), ("red", 4), ("green", 2)]]);
+        let game2 = game(2, &[&[("blue", 4), ("red", 1), ("green", 3)]]);
 
         let games = Games {
             games: vec![game1, game2],
         };
 
-        assert_eq!(games.get_total_power(), 60);
+        assert_eq!(games.get_total_power(&parse_bag(DEFAULT_BAG).unwrap()), 60);
     }
 
     #[test]
     fn test_game_from_line() {
         let line1 = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green";
-        let game1 = Game::new(1, 6, 4, 2);
+        let game1 = game(1, &[&[("blue", 3), ("red", 4)], &[("red", 1), ("green", 2), ("blue", 6)], &[("green", 2)]]);
         assert_eq!(Game::from_line(line1), game1);
 
         let line2 = "Game 2: 2 blue, 3 red; 1 red, 1 green; 1 green";
-        let game2 = Game::new(2, 2, 3, 1);
+        let game2 = game(2, &[&[("blue", 2), ("red", 3)], &[("red", 1), ("green", 1)], &[("green", 1)]]);
         assert_eq!(Game::from_line(line2), game2);
 
         let line3 = "Game 3: 1 blue, 2 red; 3 green";
-        let game3 = Game::new(3, 1, 2, 3);
+        let game3 = game(3, &[&[("blue", 1), ("red", 2)], &[("green", 3)]]);
         assert_eq!(Game::from_line(line3), game3);
     }
 
+    #[test]
+    fn test_game_min_bag() {
+        let game = Game::from_line("Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green");
+        assert_eq!(game.min_bag(), colours(&[("blue", 6), ("red", 4), ("green", 2)]));
+    }
+
+    #[test]
+    fn test_game_draw_queries() {
+        let game = Game::from_line("Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green");
+
+        assert_eq!(game.draws.len(), 3);
+        assert_eq!(game.total_cubes(), 18);
+        assert_eq!(game.cubes_seen(), colours(&[("blue", 9), ("red", 5), ("green", 4)]));
+        assert_eq!(game.most_frequent_colour(), Some(("blue".to_string(), 9)));
+
+        // Ties go to the alphabetically first colour
+        let tied = Game::from_line("Game 2: 2 red, 2 blue");
+        assert_eq!(tied.most_frequent_colour(), Some(("blue".to_string(), 2)));
+    }
+
+    #[test]
+    fn test_game_estimate_bag() {
+        let game = Game::from_line("Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green");
+
+        // A bag exactly the size of the minimum bag pins every count to it
+        let estimates = game.estimate_bag(12, 0.95).unwrap();
+        let counts: Vec<_> = estimates.iter().map(|e| (e.colour.as_str(), e.count_lower, e.count, e.count_upper)).collect();
+        assert_eq!(counts, vec![("blue", 6, 6, 6), ("green", 2, 2, 2), ("red", 4, 4, 4)]);
+
+        let estimates = game.estimate_bag(36, 0.95).unwrap();
+        assert_eq!(estimates[0].count, 18);
+        assert!(estimates[0].count_lower < estimates[0].count_upper);
+        assert!(game.estimate_bag(10, 0.95).is_err());
+    }
+
     #[test]
     fn test_game_power() {
-        let game1 = Game::new(1, 6, 4, 2);
-        assert_eq!(game1.get_game_power(), 48);
+        let bag = parse_bag(DEFAULT_BAG).unwrap();
+
+        let game1 = game(1, &[&[("blue", 6), ("red", 4), ("green", 2)]]);
+        assert_eq!(game1.get_game_power(&bag), 48);
+
+        let game2 = game(2, &[&[("blue", 4), ("red", 1), ("green", 3)]]);
+        assert_eq!(game2.get_game_power(&bag), 12);
 
-        let game2 = Game::new(2, 4, 1, 3);
-        assert_eq!(game2.get_game_power(), 12);
+        // No blue cubes drawn means a power of zero, not red x green
+        let game3 = game(3, &[&[("red", 4), ("green", 2)], &[("red", 1)]]);
+        assert_eq!(game3.get_game_power(&bag), 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_output() {
+        assert_eq!(Output::try_from("id-sum"), Ok(Output::IdSum));
+        assert_eq!(
+            Output::try_from("sum"),
+            Err("Unknown output 'sum', expected list, count, id-sum or power-sum".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_bag() {
+        assert_eq!(parse_bag("red=12,green=13,blue=14"), Ok(colours(&[("red", 12), ("green", 13), ("blue", 14)])));
+        assert_eq!(parse_bag("yellow=3"), Ok(colours(&[("yellow", 3)])));
+        assert!(parse_bag("red").is_err());
+        assert!(parse_bag("red=x").is_err());
+        assert!(parse_bag("red=1,red=2").is_err());
+    }
+}
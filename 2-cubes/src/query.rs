@@ -0,0 +1,376 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::Game;
+
+// A filter over games, e.g. `red <= 12 and (green > 5 or blue == 0)`.
+//
+// Fields are `id`, `draws` (number of draws), `cubes` (total cubes seen),
+// `power` (over the colours of the bag), or one of the known colours, which resolves to that colour's count
+// in the game's minimum bag (0 if never seen). Any other name is an error.
+#[derive(Debug, PartialEq)]
+pub enum Expr {
+    Compare(Operand, Comparison, Operand),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Operand {
+    Field(String),
+    Number(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+const FIELDS: [&str; 4] = ["id", "draws", "cubes", "power"];
+
+#[derive(Debug, PartialEq)]
+pub struct QueryError {
+    // Counted in characters from the start of the query
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(usize),
+    Compare(Comparison),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Ident(name) => write!(f, "'{}'", name),
+            Token::Number(number) => write!(f, "'{}'", number),
+            Token::Compare(comparison) => write!(f, "'{}'", comparison),
+            Token::And => write!(f, "'and'"),
+            Token::Or => write!(f, "'or'"),
+            Token::Not => write!(f, "'not'"),
+            Token::Open => write!(f, "'('"),
+            Token::Close => write!(f, "')'"),
+        }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match self {
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Greater => ">",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Equal => "==",
+            Comparison::NotEqual => "!=",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+impl Comparison {
+    fn apply(&self, left: usize, right: usize) -> bool {
+        match self {
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right,
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(position, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' || c == ')' {
+            chars.next();
+            tokens.push((position, if c == '(' { Token::Open } else { Token::Close }));
+        } else if "<>=!".contains(c) {
+            chars.next();
+            let followed_by_equals = chars.next_if(|&(_, next)| next == '=').is_some();
+            let comparison = match (c, followed_by_equals) {
+                ('<', false) => Comparison::Less,
+                ('<', true) => Comparison::LessOrEqual,
+                ('>', false) => Comparison::Greater,
+                ('>', true) => Comparison::GreaterOrEqual,
+                ('=', true) => Comparison::Equal,
+                ('!', true) => Comparison::NotEqual,
+                _ => return Err(QueryError { position, message: format!("Unknown operator '{}', expected one of < <= > >= == !=", c) }),
+            };
+            tokens.push((position, Token::Compare(comparison)));
+        } else if c.is_ascii_digit() {
+            let mut number = String::new();
+            while let Some((_, digit)) = chars.next_if(|(_, next)| next.is_ascii_digit()) {
+                number.push(digit);
+            }
+            let number = number.parse()
+                .map_err(|_| QueryError { position, message: format!("Number '{}' is too large", number) })?;
+            tokens.push((position, Token::Number(number)));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut word = String::new();
+            while let Some((_, letter)) = chars.next_if(|(_, next)| next.is_alphanumeric() || *next == '_' || *next == '-') {
+                word.push(letter);
+            }
+            let token = match word.as_str() {
+                "and" => Token::And,
+                "or" => Token::Or,
+                "not" => Token::Not,
+                _ => Token::Ident(word),
+            };
+            tokens.push((position, token));
+        } else {
+            return Err(QueryError { position, message: format!("Unexpected character '{}'", c) });
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    end: usize,
+    colours: &'a [String],
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(_, token)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.next).map(|(position, _)| *position).unwrap_or(self.end)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.next).map(|(_, token)| token.clone());
+        self.next += 1;
+        token
+    }
+
+    fn error(&self, expected: &str) -> QueryError {
+        let found = match self.peek() {
+            Some(token) => token.to_string(),
+            None => "end of query".to_string(),
+        };
+        QueryError { position: self.position(), message: format!("Expected {}, found {}", expected, found) }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.advance();
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.parse_not()?;
+        while self.peek() == Some(&Token::And) {
+            self.advance();
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, QueryError> {
+        if self.peek() == Some(&Token::Not) {
+            self.advance();
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, QueryError> {
+        if self.peek() == Some(&Token::Open) {
+            self.advance();
+            let expr = self.parse_or()?;
+            if self.peek() != Some(&Token::Close) {
+                return Err(self.error("')'"));
+            }
+            self.advance();
+            return Ok(expr);
+        }
+
+        let left = self.parse_operand()?;
+        let comparison = match self.peek() {
+            Some(Token::Compare(comparison)) => *comparison,
+            _ => return Err(self.error("a comparison operator")),
+        };
+        self.advance();
+        let right = self.parse_operand()?;
+
+        Ok(Expr::Compare(left, comparison, right))
+    }
+
+    fn parse_operand(&mut self) -> Result<Operand, QueryError> {
+        match self.peek() {
+            Some(Token::Ident(name)) => {
+                if !FIELDS.contains(&name.as_str()) && !self.colours.contains(name) {
+                    return Err(QueryError {
+                        position: self.position(),
+                        message: format!(
+                            "Unknown field '{}', expected one of {}, {}",
+                            name, FIELDS.join(", "), self.colours.join(", ")
+                        ),
+                    });
+                }
+                let name = name.clone();
+                self.advance();
+                Ok(Operand::Field(name))
+            },
+            Some(Token::Number(number)) => {
+                let number = *number;
+                self.advance();
+                Ok(Operand::Number(number))
+            },
+            _ => Err(self.error("a field name or number")),
+        }
+    }
+}
+
+impl Expr {
+    // Parse a query whose colour fields must be among `colours`
+    pub fn parse(input: &str, colours: &[String]) -> Result<Expr, QueryError> {
+        Expr::parse_tokens(input, colours).map_err(|e| QueryError {
+            // Tokens are found by byte offset, but carets line up by character
+            position: input[..e.position].chars().count(),
+            message: e.message,
+        })
+    }
+
+    fn parse_tokens(input: &str, colours: &[String]) -> Result<Expr, QueryError> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            next: 0,
+            end: input.len(),
+            colours,
+        };
+
+        let expr = parser.parse_or()?;
+        if parser.peek().is_some() {
+            return Err(parser.error("'and', 'or' or end of query"));
+        }
+
+        Ok(expr)
+    }
+
+    // `bag` is the bag whose colours `power` multiplies
+    pub fn matches(&self, game: &Game, bag: &HashMap<String, usize>) -> bool {
+        match self {
+            Expr::Compare(left, comparison, right) => comparison.apply(left.value(game, bag), right.value(game, bag)),
+            Expr::And(left, right) => left.matches(game, bag) && right.matches(game, bag),
+            Expr::Or(left, right) => left.matches(game, bag) || right.matches(game, bag),
+            Expr::Not(expr) => !expr.matches(game, bag),
+        }
+    }
+}
+
+impl Operand {
+    fn value(&self, game: &Game, bag: &HashMap<String, usize>) -> usize {
+        match self {
+            Operand::Number(number) => *number,
+            Operand::Field(name) => match name.as_str() {
+                "id" => game.id,
+                "draws" => game.draws.len(),
+                "cubes" => game.total_cubes(),
+                "power" => game.get_game_power(bag),
+                colour => *game.min_bag().get(colour).unwrap_or(&0),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(name: &str) -> Operand {
+        Operand::Field(name.to_string())
+    }
+
+    fn parse(input: &str) -> Result<Expr, QueryError> {
+        let colours: Vec<String> = ["blue", "green", "red", "yellow"].iter().map(|colour| colour.to_string()).collect();
+        Expr::parse(input, &colours)
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        let expr = parse("red <= 12 and (green > 5 or blue == 0)").unwrap();
+        assert_eq!(expr, Expr::And(
+            Box::new(Expr::Compare(field("red"), Comparison::LessOrEqual, Operand::Number(12))),
+            Box::new(Expr::Or(
+                Box::new(Expr::Compare(field("green"), Comparison::Greater, Operand::Number(5))),
+                Box::new(Expr::Compare(field("blue"), Comparison::Equal, Operand::Number(0))),
+            )),
+        ));
+
+        // and binds tighter than or
+        let expr = parse("not id < 2 or id > 3 and draws != 1").unwrap();
+        assert!(matches!(expr, Expr::Or(left, right) if matches!(*left, Expr::Not(_)) && matches!(*right, Expr::And(_, _))));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse("red <"), Err(QueryError { position: 5, message: "Expected a field name or number, found end of query".to_string() }));
+        assert_eq!(parse("red 12"), Err(QueryError { position: 4, message: "Expected a comparison operator, found '12'".to_string() }));
+        assert_eq!(parse("(red < 1"), Err(QueryError { position: 8, message: "Expected ')', found end of query".to_string() }));
+        assert_eq!(parse("red = 1").unwrap_err().position, 4);
+        assert_eq!(parse("red < 1 blue").unwrap_err().message, "Expected 'and', 'or' or end of query, found 'blue'");
+        assert_eq!(parse("red < 1 & blue < 2").unwrap_err().message, "Unexpected character '&'");
+
+        // Typos are caught rather than read as a colour never seen
+        assert_eq!(parse("id > 1 and rde > 3"), Err(QueryError {
+            position: 11,
+            message: "Unknown field 'rde', expected one of id, draws, cubes, power, blue, green, red, yellow".to_string(),
+        }));
+        assert_eq!(parse("powr > 3").unwrap_err().position, 0);
+        // Positions count characters, not bytes
+        let colours = vec!["grün".to_string()];
+        assert_eq!(Expr::parse("grün > 1 and rde > 2", &colours).unwrap_err().position, 13);
+    }
+
+    #[test]
+    fn test_matches() {
+        let game = Game::from_line("Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red");
+        let bag = crate::parse_bag(crate::DEFAULT_BAG).unwrap();
+
+        assert!(parse("red > 12").unwrap().matches(&game, &bag));
+        assert!(parse("red <= 20 and (green > 5 or blue == 0)").unwrap().matches(&game, &bag));
+        assert!(parse("draws >= 3 and id == 3").unwrap().matches(&game, &bag));
+        assert!(parse("power > 1000").unwrap().matches(&game, &bag));
+        assert!(parse("yellow == 0 and 62 == cubes").unwrap().matches(&game, &bag));
+        assert!(!parse("not red > 12").unwrap().matches(&game, &bag));
+
+        // Power multiplies the colours of the bag, so yellow counts as zero
+        let yellow = crate::parse_bag("red=20,yellow=1").unwrap();
+        assert!(parse("power == 0").unwrap().matches(&game, &yellow));
+    }
+}
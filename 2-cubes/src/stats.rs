@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::fmt;
+
+// Bag estimate for one colour. Proportions treat every cube shown as an
+// independent sample drawn with replacement, although a handful really comes
+// out without replacement, with a uniform Dirichlet prior over the colours
+// seen; counts scale those proportions to the assumed bag size.
+#[derive(Debug, PartialEq)]
+pub struct ColourEstimate {
+    pub colour: String,
+    pub observed: usize,
+    pub proportion: f64,
+    pub posterior_mean: f64,
+    pub lower: f64,
+    pub upper: f64,
+    pub count: usize,
+    pub count_lower: usize,
+    pub count_upper: usize,
+}
+
+impl fmt::Display for ColourEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: seen {}, share {:.3} (posterior {:.3}, interval {:.3}-{:.3}), bag ~{} ({}-{})",
+            self.colour, self.observed, self.proportion, self.posterior_mean,
+            self.lower, self.upper, self.count, self.count_lower, self.count_upper
+        )
+    }
+}
+
+// Estimate the bag from cubes seen per colour, sorted by colour name.
+// `min_bag` bounds each count from below, since the bag held at least that many.
+pub fn estimate_bag(
+    observed: &HashMap<String, usize>,
+    min_bag: &HashMap<String, usize>,
+    bag_size: usize,
+    confidence: f64,
+) -> Result<Vec<ColourEstimate>, String> {
+    if !(0.0..1.0).contains(&confidence) || confidence == 0.0 {
+        return Err(format!("Confidence must be between 0 and 1, got {}", confidence));
+    }
+
+    let min_total: usize = min_bag.values().sum();
+    if bag_size < min_total {
+        return Err(format!("A bag of {} cubes cannot hold the minimum bag of {} cubes", bag_size, min_total));
+    }
+
+    let mut colours: Vec<&String> = observed.keys().collect();
+    colours.sort();
+
+    let total: usize = observed.values().sum();
+    let alpha_total = (total + colours.len()) as f64;
+    let tail = (1.0 - confidence) / 2.0;
+
+    let estimates = colours.into_iter().map(|colour| {
+        let seen = observed[colour];
+        let min_count = *min_bag.get(colour).unwrap_or(&0);
+        // Room left once every other colour has its minimum
+        let max_count = bag_size - (min_total - min_count);
+
+        // Marginal of Dirichlet(1 + counts) for one colour is a Beta distribution
+        let a = seen as f64 + 1.0;
+        let b = alpha_total - a;
+        let lower = beta_quantile(tail, a, b);
+        let upper = beta_quantile(1.0 - tail, a, b);
+        let proportion = if total == 0 { 0.0 } else { seen as f64 / total as f64 };
+
+        let scale = |p: f64| ((p * bag_size as f64).round() as usize).clamp(min_count, max_count);
+
+        ColourEstimate {
+            colour: colour.clone(),
+            observed: seen,
+            proportion,
+            posterior_mean: a / alpha_total,
+            lower,
+            upper,
+            count: scale(proportion),
+            count_lower: scale(lower),
+            count_upper: scale(upper),
+        }
+    }).collect();
+
+    Ok(estimates)
+}
+
+// Lanczos approximation of ln(Γ(x)) for x > 0
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // Reflection formula
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let sum = COEFFICIENTS.iter().enumerate().skip(1)
+        .fold(COEFFICIENTS[0], |sum, (i, c)| sum + c / (x + i as f64));
+
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+// Continued fraction for the incomplete beta function (modified Lentz's method)
+fn beta_continued_fraction(x: f64, a: f64, b: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < TINY { d = TINY }
+    d = 1.0 / d;
+    let mut result = d;
+
+    for m in 1..300 {
+        let m = m as f64;
+
+        for numerator in [
+            m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m)),
+            -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0)),
+        ] {
+            d = 1.0 + numerator * d;
+            if d.abs() < TINY { d = TINY }
+            c = 1.0 + numerator / c;
+            if c.abs() < TINY { c = TINY }
+            d = 1.0 / d;
+            result *= d * c;
+        }
+
+        if (d * c - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+
+    result
+}
+
+// Regularised incomplete beta function I_x(a, b), the Beta(a, b) CDF
+fn regularized_beta(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(x, a, b) / a
+    } else {
+        1.0 - front * beta_continued_fraction(1.0 - x, b, a) / b
+    }
+}
+
+// Inverse of the Beta(a, b) CDF by bisection
+fn beta_quantile(p: f64, a: f64, b: f64) -> f64 {
+    if b <= 0.0 {
+        // Only one colour seen: its share is certainly 1
+        return 1.0;
+    }
+
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..100 {
+        let mid = (low + high) / 2.0;
+        if regularized_beta(mid, a, b) < p {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+
+    (low + high) / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::colours;
+
+    #[test]
+    fn test_regularized_beta() {
+        // Beta(1, 1) is uniform
+        assert!((regularized_beta(0.3, 1.0, 1.0) - 0.3).abs() < 1e-12);
+        // Beta(2, 1) has CDF x^2
+        assert!((regularized_beta(0.5, 2.0, 1.0) - 0.25).abs() < 1e-12);
+        // Symmetric distributions have their median at 0.5
+        assert!((regularized_beta(0.5, 7.0, 7.0) - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_beta_quantile() {
+        assert!((beta_quantile(0.025, 1.0, 1.0) - 0.025).abs() < 1e-9);
+        assert!((beta_quantile(0.25, 2.0, 1.0) - 0.5).abs() < 1e-9);
+        assert_eq!(beta_quantile(0.5, 3.0, 0.0), 1.0);
+    }
+
+    #[test]
+    fn test_estimate_bag() {
+        let observed = colours(&[("blue", 9), ("green", 4), ("red", 5)]);
+        let min_bag = colours(&[("blue", 6), ("green", 2), ("red", 4)]);
+
+        let estimates = estimate_bag(&observed, &min_bag, 36, 0.95).unwrap();
+
+        assert_eq!(estimates.iter().map(|e| e.colour.as_str()).collect::<Vec<_>>(), vec!["blue", "green", "red"]);
+        assert_eq!(estimates[0].proportion, 0.5);
+        assert_eq!(estimates[0].count, 18);
+        assert!((estimates[0].posterior_mean - 10.0 / 21.0).abs() < 1e-12);
+        for estimate in &estimates {
+            assert!(estimate.lower < estimate.posterior_mean && estimate.posterior_mean < estimate.upper);
+            assert!(estimate.count_lower <= estimate.count && estimate.count <= estimate.count_upper);
+        }
+        // Never below what was drawn in one handful
+        assert!(estimates[1].count_lower >= 2);
+    }
+
+    #[test]
+    fn test_estimate_bag_errors() {
+        let observed = colours(&[("blue", 9)]);
+        let min_bag = colours(&[("blue", 6)]);
+
+        assert!(estimate_bag(&observed, &min_bag, 5, 0.95).is_err());
+        assert!(estimate_bag(&observed, &min_bag, 6, 1.5).is_err());
+    }
+}
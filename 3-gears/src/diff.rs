@@ -0,0 +1,172 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::{ComponentType, GearRule, Schematic, SchematicComponent, SchematicError};
+
+// What changed between two revisions of a schematic
+#[derive(Debug, Default, PartialEq)]
+pub struct SchematicDiff {
+    pub added: Vec<SchematicComponent>,
+    pub removed: Vec<SchematicComponent>,
+    // Old and new placement of components that only changed position
+    pub moved: Vec<(SchematicComponent, SchematicComponent)>,
+    // Parts present in both revisions (as in the new one) whose part status changed
+    pub became_parts: Vec<SchematicComponent>,
+    pub stopped_being_parts: Vec<SchematicComponent>,
+    pub part_sum: (usize, usize),
+    pub gear_ratio_total: (usize, usize),
+}
+
+// Compare two schematics. Components of the same type that disappear from one
+// place and appear in another are paired up as moves, closest first.
+pub fn diff(old: &Schematic, new: &Schematic, rules: &[GearRule]) -> Result<SchematicDiff, SchematicError> {
+    let old_components: HashSet<&SchematicComponent> = old.components.components.iter().collect();
+    let new_components: HashSet<&SchematicComponent> = new.components.components.iter().collect();
+
+    let mut removed_by_type: HashMap<ComponentType, Vec<SchematicComponent>> = HashMap::new();
+    for component in old_components.difference(&new_components) {
+        removed_by_type.entry(component.component).or_default().push(**component);
+    }
+    let mut added_by_type: HashMap<ComponentType, Vec<SchematicComponent>> = HashMap::new();
+    for component in new_components.difference(&old_components) {
+        added_by_type.entry(component.component).or_default().push(**component);
+    }
+
+    let mut result = SchematicDiff::default();
+
+    for (component_type, removed) in removed_by_type {
+        let added = added_by_type.remove(&component_type).unwrap_or_default();
+
+        let mut pairs: Vec<(usize, SchematicComponent, SchematicComponent)> = removed.iter()
+            .flat_map(|from| added.iter().map(move |to| (from.x.abs_diff(to.x).max(from.y.abs_diff(to.y)), *from, *to)))
+            .collect();
+        pairs.sort_by_key(|(distance, from, to)| (*distance, from.y, from.x, to.y, to.x));
+
+        let mut paired_from = HashSet::new();
+        let mut paired_to = HashSet::new();
+        for (_, from, to) in pairs {
+            if !paired_from.contains(&from) && !paired_to.contains(&to) {
+                paired_from.insert(from);
+                paired_to.insert(to);
+                result.moved.push((from, to));
+            }
+        }
+
+        result.removed.extend(removed.into_iter().filter(|from| !paired_from.contains(from)));
+        result.added.extend(added.into_iter().filter(|to| !paired_to.contains(to)));
+    }
+    result.added.extend(added_by_type.into_values().flatten());
+
+    // Part status of every part that survived, possibly after moving
+    let old_part_numbers = old.get_part_numbers();
+    let new_part_numbers = new.get_part_numbers();
+    let unchanged = old_components.intersection(&new_components).map(|component| (**component, **component));
+    for (from, to) in unchanged.chain(result.moved.iter().copied()) {
+        if let ComponentType::Part(_) = to.component {
+            match (old_part_numbers.contains(&from), new_part_numbers.contains(&to)) {
+                (false, true) => result.became_parts.push(to),
+                (true, false) => result.stopped_being_parts.push(to),
+                _ => {},
+            }
+        }
+    }
+
+    result.part_sum = (old.get_part_numbers_sum(), new.get_part_numbers_sum());
+    result.gear_ratio_total = (old.get_gear_ratios(rules)?, new.get_gear_ratios(rules)?);
+
+    let by_position = |component: &SchematicComponent| (component.y, component.x);
+    result.added.sort_by_key(by_position);
+    result.removed.sort_by_key(by_position);
+    result.moved.sort_by_key(|(from, _)| by_position(from));
+    result.became_parts.sort_by_key(by_position);
+    result.stopped_being_parts.sort_by_key(by_position);
+
+    Ok(result)
+}
+
+fn kind(component: &SchematicComponent) -> &'static str {
+    match component.component {
+        ComponentType::Part(_) => "part",
+        ComponentType::Symbol(_) => "symbol",
+    }
+}
+
+// Signed change between two totals, e.g. "+35" or "-114"
+fn change((old, new): (usize, usize)) -> String {
+    if new >= old {
+        format!("+{}", new - old)
+    } else {
+        format!("-{}", old - new)
+    }
+}
+
+impl fmt::Display for SchematicDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for component in &self.added {
+            writeln!(f, "Added {} {} at {}, {}", kind(component), component.component, component.x, component.y)?;
+        }
+        for component in &self.removed {
+            writeln!(f, "Removed {} {} at {}, {}", kind(component), component.component, component.x, component.y)?;
+        }
+        for (from, to) in &self.moved {
+            writeln!(f, "Moved {} {} from {}, {} to {}, {}", kind(from), from.component, from.x, from.y, to.x, to.y)?;
+        }
+        for part in &self.became_parts {
+            writeln!(f, "{} at {}, {} is now a part number", part.component, part.x, part.y)?;
+        }
+        for part in &self.stopped_being_parts {
+            writeln!(f, "{} at {}, {} is no longer a part number", part.component, part.x, part.y)?;
+        }
+        writeln!(f, "Part number sum: {} -> {} ({})", self.part_sum.0, self.part_sum.1, change(self.part_sum))?;
+        writeln!(f, "Gear ratio total: {} -> {} ({})", self.gear_ratio_total.0, self.gear_ratio_total.1, change(self.gear_ratio_total))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SchematicConfig;
+
+    fn schematic(input: &str) -> Schematic {
+        crate::tests::schematic(input, SchematicConfig::default())
+    }
+
+    #[test]
+    fn test_diff_unchanged() {
+        let old = schematic("467..114..\n...*......\n..35......");
+        let diff = diff(&old, &old, &[GearRule::default()]).unwrap();
+
+        assert_eq!(diff, SchematicDiff {
+            part_sum: (502, 502),
+            gear_ratio_total: (16345, 16345),
+            ..SchematicDiff::default()
+        });
+    }
+
+    #[test]
+    fn test_diff_changes() {
+        let old = schematic("467..114..\n...*......\n..35...#..");
+        let new = schematic("467.114...\n...*......\n..35......\n.......7..");
+        let diff = diff(&old, &new, &[GearRule::default()]).unwrap();
+
+        assert_eq!(diff.added, vec![SchematicComponent::new(7, 3, ComponentType::Part(7))]);
+        assert_eq!(diff.removed, vec![SchematicComponent::new(7, 2, ComponentType::Symbol('#'))]);
+        assert_eq!(diff.moved, vec![
+            (SchematicComponent::new(5, 0, ComponentType::Part(114)), SchematicComponent::new(4, 0, ComponentType::Part(114))),
+        ]);
+        assert_eq!(diff.became_parts, vec![SchematicComponent::new(4, 0, ComponentType::Part(114))]);
+        assert_eq!(diff.stopped_being_parts, vec![]);
+        // 114 joins the gear, so it has three parts and stops being a gear
+        assert_eq!(diff.part_sum, (502, 616));
+        assert_eq!(diff.gear_ratio_total, (16345, 0));
+
+        assert_eq!(diff.to_string(), "\
+Added part 7 at 7, 3
+Removed symbol # at 7, 2
+Moved part 114 from 5, 0 to 4, 0
+114 at 4, 0 is now a part number
+Part number sum: 502 -> 616 (+114)
+Gear ratio total: 16345 -> 0 (-16345)
+");
+    }
+}
@@ -1,11 +1,19 @@
+mod diff;
+mod render;
+
 use std::collections::HashSet;
-use std::{env, fmt};
+use std::{env, fmt, str::FromStr};
 use std::{collections::HashMap, fs::File};
 use std::io::{BufRead, BufReader};
 
+use render::RenderFormat;
+
 #[derive(Debug)]
 struct Schematic {
     components: IndexedComponentList,
+    config: SchematicConfig,
+    // Width of the widest row and number of rows read
+    size: (X, Y),
 }
 
 type X = usize;
@@ -16,6 +24,10 @@ struct IndexedComponentList {
     components: Vec<SchematicComponent>,
     coord_index: HashMap<(X, Y), SchematicComponent>,
     type_index: HashMap<ComponentType, Vec<SchematicComponent>>,
+    // Where each component sits in `components`, by its starting position
+    list_index: HashMap<(X, Y), usize>,
+    // Row width that parts wrap at, if they may continue onto the next row
+    wrap_width: Option<X>,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
@@ -31,6 +43,82 @@ enum ComponentType {
     Symbol(char),
 }
 
+#[derive(Debug, PartialEq)]
+enum EditError {
+    // Another component already covers this position
+    Overlap { position: (X, Y), existing: SchematicComponent },
+    NotFound(SchematicComponent),
+}
+
+#[derive(Debug, PartialEq)]
+enum SchematicError {
+    Read { row: Y, message: String },
+    // Rows must all be the same width when parts wrap
+    RowWidth { row: Y, width: X, expected: X },
+    UnknownSymbol { position: (X, Y), symbol: char },
+    BadNumber { position: (X, Y), digits: String },
+    // A gear ratio, or the running total of them, doesn't fit in a usize
+    RatioOverflow { position: (X, Y) },
+    Edit(EditError),
+}
+
+// Which characters count as symbols; anything that is neither a digit, a
+// blank nor a symbol is rejected when parsing
+#[derive(Clone, Debug, PartialEq)]
+enum SymbolSet {
+    Only(HashSet<char>),
+    AnyExceptBlanks,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Adjacency {
+    // Left, right, above and below
+    Orthogonal,
+    // Orthogonal plus diagonals
+    EightWay,
+    // Every cell within the given distance in both directions
+    Chebyshev(usize),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct SchematicConfig {
+    symbols: SymbolSet,
+    blanks: HashSet<char>,
+    adjacency: Adjacency,
+    wrap_parts: bool,
+}
+
+// How many adjacent parts a symbol needs to count as a gear
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PartCount {
+    Exact(usize),
+    Min(usize),
+    Max(usize),
+}
+
+// How the adjacent part numbers combine into a gear ratio
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Combine {
+    Product,
+    Sum,
+    Max,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct GearRule {
+    symbol: char,
+    parts: PartCount,
+    combine: Combine,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Gear {
+    symbol: SchematicComponent,
+    rule: GearRule,
+    parts: Vec<SchematicComponent>,
+    ratio: usize,
+}
+
 impl fmt::Display for ComponentType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -40,82 +128,265 @@ impl fmt::Display for ComponentType {
     }
 }
 
-impl Schematic {
-    fn possible_symbols() -> [char; 11] {
-        ['.', '&', '-', '=', '$', '+', '#', '%', '*', '/', '@']
+impl Default for SchematicConfig {
+    fn default() -> Self {
+        SchematicConfig {
+            symbols: SymbolSet::Only(HashSet::from(['&', '-', '=', '$', '+', '#', '%', '*', '/', '@'])),
+            blanks: HashSet::from(['.']),
+            adjacency: Adjacency::EightWay,
+            wrap_parts: false,
+        }
     }
-    fn new(components: Vec<SchematicComponent>) -> Schematic {
-        let mut component_list = IndexedComponentList::new();
-        component_list.set_component(components);
-        Schematic {
-            components: component_list,
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EditError::Overlap { position, existing } => write!(
+                f, "{} at {}, {} already covers {}, {}",
+                existing.component, existing.x, existing.y, position.0, position.1
+            ),
+            EditError::NotFound(component) => write!(
+                f, "No {} at {}, {}", component.component, component.x, component.y
+            ),
+        }
+    }
+}
+
+impl fmt::Display for SchematicError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchematicError::Read { row, message } => write!(f, "Failed to read row {}: {}", row, message),
+            SchematicError::RowWidth { row, width, expected } => write!(
+                f, "Rows must all be the same width to wrap parts, row {} has {} characters, expected {}", row, width, expected
+            ),
+            SchematicError::UnknownSymbol { position, symbol } => write!(
+                f, "Unknown symbol type {} at {}, {}", symbol, position.0, position.1
+            ),
+            SchematicError::BadNumber { position, digits } => write!(
+                f, "Unparsable number {} at {}, {}", digits, position.0, position.1
+            ),
+            SchematicError::RatioOverflow { position } => write!(
+                f, "Gear ratio overflows at {}, {}", position.0, position.1
+            ),
+            SchematicError::Edit(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl ComponentType {
+    fn part_number(&self) -> Option<usize> {
+        match self {
+            ComponentType::Part(part_number) => Some(*part_number),
+            ComponentType::Symbol(_) => None,
+        }
+    }
+}
+
+impl PartCount {
+    fn allows(&self, count: usize) -> bool {
+        match self {
+            PartCount::Exact(required) => count == *required,
+            PartCount::Min(required) => count >= *required,
+            PartCount::Max(required) => count <= *required,
+        }
+    }
+}
+
+impl Combine {
+    // None if the ratio overflows
+    fn apply(&self, mut part_numbers: impl Iterator<Item = usize>) -> Option<usize> {
+        match self {
+            Combine::Product => part_numbers.try_fold(1usize, |ratio, part_number| ratio.checked_mul(part_number)),
+            Combine::Sum => part_numbers.try_fold(0usize, |ratio, part_number| ratio.checked_add(part_number)),
+            Combine::Max => Some(part_numbers.max().unwrap_or(0)),
+        }
+    }
+}
+
+impl Default for GearRule {
+    // A '*' next to exactly two parts, multiplied together
+    fn default() -> Self {
+        GearRule {
+            symbol: '*',
+            parts: PartCount::Exact(2),
+            combine: Combine::Product,
+        }
+    }
+}
+
+impl FromStr for GearRule {
+    type Err = String;
+
+    // Parse "<symbol>:<count>:<combine>", where count is "2", "=2", ">=2" or "<=2"
+    // and combine is "product", "sum" or "max"
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let mut fields = rule.split(':');
+        let (Some(symbol), Some(count), Some(combine), None) = (fields.next(), fields.next(), fields.next(), fields.next()) else {
+            return Err(format!("Gear rule '{}' should look like <symbol>:<count>:<combine>", rule));
+        };
+
+        let mut symbol_chars = symbol.chars();
+        let (Some(symbol), None) = (symbol_chars.next(), symbol_chars.next()) else {
+            return Err(format!("Gear rule '{}' should have a single character symbol", rule));
+        };
+
+        let parse_count = |count: &str| count.parse::<usize>()
+            .map_err(|_| format!("Gear rule '{}' has an invalid part count", rule));
+        let parts = if let Some(count) = count.strip_prefix(">=") {
+            PartCount::Min(parse_count(count)?)
+        } else if let Some(count) = count.strip_prefix("<=") {
+            PartCount::Max(parse_count(count)?)
+        } else {
+            PartCount::Exact(parse_count(count.strip_prefix('=').unwrap_or(count))?)
+        };
+
+        let combine = match combine {
+            "product" => Combine::Product,
+            "sum" => Combine::Sum,
+            "max" => Combine::Max,
+            _ => return Err(format!("Gear rule '{}' has unknown combine '{}', expected product, sum or max", rule, combine)),
+        };
+
+        Ok(GearRule { symbol, parts, combine })
+    }
+}
+
+impl fmt::Display for Gear {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let operator = match self.rule.combine {
+            Combine::Product => " x ",
+            Combine::Sum => " + ",
+            Combine::Max => ", ",
+        };
+        let parts = self.parts.iter()
+            .map(|part| part.component.to_string())
+            .collect::<Vec<_>>()
+            .join(operator);
+
+        match self.rule.combine {
+            Combine::Max => write!(f, "{} at ({}, {}): max({}) = {}", self.symbol.component, self.symbol.x, self.symbol.y, parts, self.ratio),
+            _ => write!(f, "{} at ({}, {}): {} = {}", self.symbol.component, self.symbol.x, self.symbol.y, parts, self.ratio),
+        }
+    }
+}
+
+impl SymbolSet {
+    fn contains(&self, character: char) -> bool {
+        match self {
+            SymbolSet::Only(symbols) => symbols.contains(&character),
+            SymbolSet::AnyExceptBlanks => true,
+        }
+    }
+}
+
+impl Adjacency {
+    // Positions adjacent to (x, y), excluding (x, y) itself
+    fn neighbours(&self, x: X, y: Y) -> Vec<(X, Y)> {
+        let radius = match self {
+            Adjacency::Orthogonal | Adjacency::EightWay => 1,
+            Adjacency::Chebyshev(radius) => *radius,
+        };
+
+        let mut positions = Vec::new();
+        for near_y in y.saturating_sub(radius)..=y + radius {
+            for near_x in x.saturating_sub(radius)..=x + radius {
+                if (near_x, near_y) == (x, y) {
+                    continue;
+                }
+                if *self == Adjacency::Orthogonal && near_x != x && near_y != y {
+                    continue;
+                }
+                positions.push((near_x, near_y));
+            }
         }
+
+        positions
     }
+}
 
+impl Schematic {
     // Create schematic by parsing file
-    fn from_file<R: BufRead>(reader: R) -> Schematic {
+    fn from_file<R: BufRead>(reader: R, config: SchematicConfig) -> Result<Schematic, SchematicError> {
         let mut components = Vec::new();
+        let mut width = None;
+        let mut size = (0, 0);
 
-        let mut y: Y = 0;
-
-        for line in reader.lines() {
-            let line = line.expect("Failed to read line");
-            let mut x: X = 0;
-
-            let parsed_line = line.split_inclusive(Schematic::possible_symbols()).flat_map(|e| {
-                // split_inclusive leaves the delimiter at the end of the string, unless it's the end of the line
-                // If the length is > 1, and the last character is a possible_symbol, split it off
-                if e.len() > 1 {
-                    if let Some(last_character) = &e.chars().last() {
-                        if Schematic::possible_symbols().contains(last_character) {
-                            return vec![&e[..e.len()-1], &e[e.len()-1..]].into_iter()
-                        }
-                    }
+        // Digits of the part being read, and where it started
+        let mut part: Option<(X, Y, String)> = None;
+
+        for (y, line) in reader.lines().enumerate() {
+            let line = line.map_err(|e| SchematicError::Read { row: y, message: e.to_string() })?;
+            let line_width = line.chars().count();
+            size = (size.0.max(line_width), y + 1);
+
+            if config.wrap_parts {
+                let expected = *width.get_or_insert(line_width);
+                if expected != line_width {
+                    return Err(SchematicError::RowWidth { row: y, width: line_width, expected });
                 }
+            }
 
-                vec![e].into_iter()
-                
-            });
-
-            
-            for component in parsed_line {
-                match component.parse::<usize>() {
-                    Ok(part_number) => {
-                        components.push(SchematicComponent::new(x, y, ComponentType::Part(part_number)));
-                    },
-                    Err(_) => {
-                        if component.len() != 1 {
-                            panic!("Unparsable number, but not a single symbol: {}", component);
-                        }
-
-                        match component.chars().next() {
-                            Some('.') => { },
-                            Some(symbol) => {
-                                if !Schematic::possible_symbols().contains(&symbol) {
-                                    panic!("Unknown symbol type: {}", component);
-                                }
-                                components.push(SchematicComponent::new(x, y, ComponentType::Symbol(symbol)));
-                            },
-                            None => {
-                                panic!("Unknown component type: {}", component);
-                            }
-                        }
-                    },
+            for (x, character) in line.chars().enumerate() {
+                if character.is_ascii_digit() {
+                    part.get_or_insert((x, y, String::new())).2.push(character);
+                    continue;
                 }
-                x += component.len();
+
+                if let Some(part) = part.take() {
+                    components.push(Schematic::parse_part(part)?);
+                }
+
+                if config.blanks.contains(&character) {
+                    continue;
+                }
+                if !config.symbols.contains(character) {
+                    return Err(SchematicError::UnknownSymbol { position: (x, y), symbol: character });
+                }
+                components.push(SchematicComponent::new(x, y, ComponentType::Symbol(character)));
             }
-            y += 1;
+
+            // A part may only carry on to the next row if it reaches the end of this one
+            if !config.wrap_parts || !line.ends_with(|c: char| c.is_ascii_digit()) {
+                if let Some(part) = part.take() {
+                    components.push(Schematic::parse_part(part)?);
+                }
+            }
+        }
+        if let Some(part) = part.take() {
+            components.push(Schematic::parse_part(part)?);
         }
 
-        Schematic::new(components)
+        let mut component_list = IndexedComponentList::new();
+        component_list.wrap_width = if config.wrap_parts { width } else { None };
+        component_list.set_component(components).map_err(SchematicError::Edit)?;
+
+        Ok(Schematic {
+            components: component_list,
+            config,
+            size,
+        })
+    }
+
+    fn parse_part((x, y, digits): (X, Y, String)) -> Result<SchematicComponent, SchematicError> {
+        match digits.parse() {
+            Ok(part_number) => Ok(SchematicComponent::new(x, y, ComponentType::Part(part_number))),
+            Err(_) => Err(SchematicError::BadNumber { position: (x, y), digits }),
+        }
+    }
+
+    fn get_adjacent_parts(&self, x: X, y: Y) -> Vec<SchematicComponent> {
+        self.components.get_adjacent_parts(x, y, self.config.adjacency)
     }
 
     fn get_part_numbers(&self) -> HashSet<SchematicComponent> {
         let mut part_numbers = HashSet::new();
 
-        for symbol in Schematic::possible_symbols() {
-            for component in self.components.type_index.get(&ComponentType::Symbol(symbol)).into_iter().flatten() {
-                part_numbers.extend(self.components.get_adjacent_parts(component.x, component.y));
+        for (component_type, components) in &self.components.type_index {
+            if let ComponentType::Symbol(_) = component_type {
+                for component in components {
+                    part_numbers.extend(self.get_adjacent_parts(component.x, component.y));
+                }
             }
         }
 
@@ -131,22 +402,49 @@ impl Schematic {
         }).sum()
     }
 
-    fn get_gear_ratios(&self) -> usize {
-        let mut gear_ratios: usize = 0;
+    // Find every gear matching any of the rules in a single pass over the symbols,
+    // ordered by position. A symbol matching several rules yields a gear for each.
+    // A symbol with no adjacent parts is never a gear, whatever the rule allows.
+    fn get_gears(&self, rules: &[GearRule]) -> Result<Vec<Gear>, SchematicError> {
+        let mut gears = Vec::new();
+
+        for (component_type, components) in &self.components.type_index {
+            let ComponentType::Symbol(symbol) = component_type else { continue };
+            let symbol_rules: Vec<&GearRule> = rules.iter().filter(|rule| rule.symbol == *symbol).collect();
+            if symbol_rules.is_empty() {
+                continue;
+            }
 
-        for component in self.components.type_index.get(&ComponentType::Symbol('*')).into_iter().flatten() {
-            let adjacent_parts = self.components.get_adjacent_parts(component.x, component.y);
+            for component in components {
+                let mut adjacent_parts = self.get_adjacent_parts(component.x, component.y);
+                if adjacent_parts.is_empty() {
+                    continue;
+                }
+                adjacent_parts.sort_by_key(|part| (part.y, part.x));
 
-            if adjacent_parts.len() == 2 {
-                let gear_ratio_a: usize = adjacent_parts[0].component.to_string().parse().expect("Failed to parse gear ratio");
-                let gear_ratio_b: usize = adjacent_parts[1].component.to_string().parse().expect("Failed to parse gear ratio");
-                gear_ratios += gear_ratio_a * gear_ratio_b;
+                for rule in symbol_rules.iter().filter(|rule| rule.parts.allows(adjacent_parts.len())) {
+                    let part_numbers = adjacent_parts.iter().filter_map(|part| part.component.part_number());
+                    let ratio = rule.combine.apply(part_numbers)
+                        .ok_or(SchematicError::RatioOverflow { position: (component.x, component.y) })?;
+                    gears.push(Gear {
+                        symbol: *component,
+                        rule: **rule,
+                        parts: adjacent_parts.clone(),
+                        ratio,
+                    });
+                }
             }
-            
         }
-        gear_ratios
+
+        gears.sort_by_key(|gear| (gear.symbol.y, gear.symbol.x));
+        Ok(gears)
+    }
+
+    fn get_gear_ratios(&self, rules: &[GearRule]) -> Result<usize, SchematicError> {
+        self.get_gears(rules)?.iter().try_fold(0usize, |total, gear| {
+            total.checked_add(gear.ratio).ok_or(SchematicError::RatioOverflow { position: (gear.symbol.x, gear.symbol.y) })
+        })
     }
-    
 }
 
 impl IndexedComponentList {
@@ -155,83 +453,372 @@ impl IndexedComponentList {
             components: Vec::new(),
             coord_index: HashMap::new(),
             type_index: HashMap::new(),
+            list_index: HashMap::new(),
+            wrap_width: None,
         }
     }
 
-    fn set_component(&mut self, components: Vec<SchematicComponent>) {
+    fn set_component(&mut self, components: Vec<SchematicComponent>) -> Result<(), EditError> {
         self.components = components;
-        self._rebuild_indexes();
+        self._rebuild_indexes()
     }
 
-    fn get_adjacent_parts(&self, x: X, y: Y) -> Vec<SchematicComponent> {
+    // Every position a component covers, wrapping onto following rows if enabled
+    fn cells(&self, component: &SchematicComponent) -> Vec<(X, Y)> {
+        let length = component.component.to_string().chars().count();
+
+        (0..length).map(|offset| match self.wrap_width {
+            Some(width) if width > 0 => {
+                let x = component.x + offset;
+                (x % width, component.y + x / width)
+            },
+            _ => (component.x + offset, component.y),
+        }).collect()
+    }
+
+    fn get_adjacent_parts(&self, x: X, y: Y, adjacency: Adjacency) -> Vec<SchematicComponent> {
         let mut adjacent_parts = HashSet::new();
 
-        let mut positions = vec![
-            (x+1, y), // Right
-            (x+1, y+1), // Below Right
-            (x, y+1), // Below
-        ];
-        if y > 0 {
-            positions.push((x, y-1)); // Above
-            positions.push((x+1, y-1)); // Above Right
+        for position in adjacency.neighbours(x, y) {
+            if let Some(part) = self.coord_index.get(&position) {
+                if let ComponentType::Part(_) = part.component {
+                    adjacent_parts.insert(*part);
+                }
+            }
         }
-        if x > 0 {
-            positions.push((x-1, y)); // Left
-            positions.push((x-1, y+1)); // Below Left
+
+        Vec::from_iter(adjacent_parts)
+    }
+
+    // Check that a component could be placed without covering another,
+    // ignoring `ignore` (the component being moved)
+    fn check_free(&self, component: &SchematicComponent, ignore: Option<&SchematicComponent>) -> Result<(), EditError> {
+        for position in self.cells(component) {
+            if let Some(existing) = self.coord_index.get(&position) {
+                if Some(existing) != ignore {
+                    return Err(EditError::Overlap { position, existing: *existing });
+                }
+            }
         }
-        if x > 0 && y > 0 {
-            positions.push((x-1, y-1)); // Above Left
-        }        
+        Ok(())
+    }
 
-        for (x, y) in positions {
-            if let Some(part) = self.coord_index.get(&(x, y)) {
-                adjacent_parts.insert(*part);
+    fn insert(&mut self, component: SchematicComponent) -> Result<(), EditError> {
+        self.check_free(&component, None)?;
+
+        for position in self.cells(&component) {
+            self.coord_index.insert(position, component);
+        }
+        self.type_index.entry(component.component).or_default().push(component);
+        self.list_index.insert((component.x, component.y), self.components.len());
+        self.components.push(component);
+
+        Ok(())
+    }
+
+    // Where a component sits in `components`, found through the indexes
+    // rather than by scanning the list
+    fn find(&self, component: &SchematicComponent) -> Result<usize, EditError> {
+        match self.coord_index.get(&(component.x, component.y)) {
+            Some(existing) if existing == component => Ok(self.list_index[&(component.x, component.y)]),
+            _ => Err(EditError::NotFound(*component)),
+        }
+    }
+
+    // The last component takes the removed one's place in the component list
+    fn remove(&mut self, component: &SchematicComponent) -> Result<SchematicComponent, EditError> {
+        let index = self.find(component)?;
+        let component = self.components.swap_remove(index);
+        self.list_index.remove(&(component.x, component.y));
+        if let Some(last) = self.components.get(index) {
+            self.list_index.insert((last.x, last.y), index);
+        }
+
+        for position in self.cells(&component) {
+            self.coord_index.remove(&position);
+        }
+        if let Some(same_type) = self.type_index.get_mut(&component.component) {
+            same_type.retain(|existing| *existing != component);
+            if same_type.is_empty() {
+                self.type_index.remove(&component.component);
             }
         }
 
-        Vec::from_iter(adjacent_parts)
+        Ok(component)
+    }
+
+    // Move a component so it starts at (x, y), keeping its place in the component list
+    fn move_component(&mut self, component: &SchematicComponent, x: X, y: Y) -> Result<SchematicComponent, EditError> {
+        let index = self.find(component)?;
+        let moved = SchematicComponent::new(x, y, component.component);
+        self.check_free(&moved, Some(component))?;
+
+        for position in self.cells(component) {
+            self.coord_index.remove(&position);
+        }
+        for position in self.cells(&moved) {
+            self.coord_index.insert(position, moved);
+        }
+        for existing in self.type_index.get_mut(&component.component).into_iter().flatten() {
+            if existing == component {
+                *existing = moved;
+            }
+        }
+        self.components[index] = moved;
+        self.list_index.remove(&(component.x, component.y));
+        self.list_index.insert((x, y), index);
+
+        Ok(moved)
+    }
+
+    // Components covering any position in the rectangle between the two corners,
+    // inclusive, ordered by position
+    fn components_in(&self, (min_x, min_y): (X, Y), (max_x, max_y): (X, Y)) -> Vec<SchematicComponent> {
+        let area = (max_x.saturating_sub(min_x) + 1).saturating_mul(max_y.saturating_sub(min_y) + 1);
+
+        let mut found: Vec<SchematicComponent> = if area <= self.coord_index.len() {
+            let found: HashSet<SchematicComponent> = (min_y..=max_y)
+                .flat_map(|y| (min_x..=max_x).map(move |x| (x, y)))
+                .filter_map(|position| self.coord_index.get(&position).copied())
+                .collect();
+            found.into_iter().collect()
+        } else {
+            self.components.iter()
+                .filter(|component| self.cells(component).iter()
+                    .any(|(x, y)| (min_x..=max_x).contains(x) && (min_y..=max_y).contains(y)))
+                .copied()
+                .collect()
+        };
+
+        found.sort_by_key(|component| (component.y, component.x));
+        found
     }
 
-    fn _rebuild_indexes(&mut self) {
+    // The symbol closest to any cell of the part, by Chebyshev distance (1 is
+    // touching), with ties going to the first by position
+    fn nearest_symbol(&self, part: &SchematicComponent) -> Option<(SchematicComponent, usize)> {
+        let part_cells = self.cells(part);
+
+        self.type_index.iter()
+            .filter(|(component_type, _)| matches!(component_type, ComponentType::Symbol(_)))
+            .flat_map(|(_, symbols)| symbols)
+            .map(|symbol| {
+                let distance = part_cells.iter()
+                    .map(|(x, y)| x.abs_diff(symbol.x).max(y.abs_diff(symbol.y)))
+                    .min()
+                    .unwrap_or(usize::MAX);
+                (*symbol, distance)
+            })
+            .min_by_key(|(symbol, distance)| (*distance, symbol.y, symbol.x))
+    }
+
+    fn _rebuild_indexes(&mut self) -> Result<(), EditError> {
         self.coord_index.clear();
         self.type_index.clear();
+        self.list_index.clear();
         for (index, component) in self.components.iter().enumerate() {
-            for (size, _) in component.component.to_string().chars().enumerate() {
-                // Test if a component already exists at this point, if so, panic
-                if self.coord_index.contains_key(&(component.x + size, component.y)) {
-                    panic!("Component {:?} already exists at {}, {}", component.component, component.x + size, component.y);
+            for position in self.cells(component) {
+                // Test if a component already exists at this point
+                if let Some(existing) = self.coord_index.get(&position) {
+                    return Err(EditError::Overlap { position, existing: *existing });
                 }
 
-                self.coord_index.insert((component.x + size, component.y), self.components[index]);
+                self.coord_index.insert(position, *component);
             }
-            self.type_index.entry(component.component.clone()).or_insert(Vec::new()).push(self.components[index]);
+            self.type_index.entry(component.component).or_default().push(*component);
+            self.list_index.insert((component.x, component.y), index);
         }
+        Ok(())
     }
 }
 
 impl SchematicComponent {
     fn new(x: X, y: Y, component: ComponentType) -> SchematicComponent {
         SchematicComponent {
-            x: x,
-            y: y,
-            component: component,
+            x,
+            y,
+            component,
         }
     }
 }
 
+// Parse "x,y"
+fn parse_position(position: &str) -> (X, Y) {
+    let (x, y) = position.split_once(',').expect("Positions should look like x,y");
+    (x.trim().parse().expect("Failed to parse x"), y.trim().parse().expect("Failed to parse y"))
+}
+
+// Parse "x,y,component", where component is a part number or a symbol
+fn parse_component(component: &str) -> SchematicComponent {
+    let (position, value) = component.rsplit_once(',').expect("Components should look like x,y,value");
+    let (x, y) = parse_position(position);
+    let component_type = match value.parse() {
+        Ok(part_number) => ComponentType::Part(part_number),
+        Err(_) => ComponentType::Symbol(value.chars().next().expect("Please provide a part number or symbol")),
+    };
+    SchematicComponent::new(x, y, component_type)
+}
+
+// Apply an edit from the command line, reporting rather than stopping on failure
+fn apply_edit(components: &mut IndexedComponentList, edit: &Edit) {
+    let result = match edit {
+        Edit::Insert(component) => components.insert(*component),
+        Edit::Remove(x, y) => match components.coord_index.get(&(*x, *y)).copied() {
+            Some(component) => components.remove(&component).map(|_| ()),
+            None => {
+                eprintln!("Nothing to remove at {}, {}", x, y);
+                return;
+            },
+        },
+        Edit::Move((x, y), (new_x, new_y)) => match components.coord_index.get(&(*x, *y)).copied() {
+            Some(component) => components.move_component(&component, *new_x, *new_y).map(|_| ()),
+            None => {
+                eprintln!("Nothing to move at {}, {}", x, y);
+                return;
+            },
+        },
+    };
+
+    if let Err(e) = result {
+        eprintln!("Edit failed: {}", e);
+    }
+}
+
+enum Edit {
+    Insert(SchematicComponent),
+    Remove(X, Y),
+    Move((X, Y), (X, Y)),
+}
+
+// Parse "orthogonal", "eight" or "chebyshev:<radius>"
+fn parse_adjacency(adjacency: &str) -> Adjacency {
+    match adjacency.split_once(':') {
+        None if adjacency == "orthogonal" => Adjacency::Orthogonal,
+        None if adjacency == "eight" => Adjacency::EightWay,
+        Some(("chebyshev", radius)) => Adjacency::Chebyshev(radius.parse().expect("Failed to parse chebyshev radius")),
+        _ => panic!("Unknown adjacency {}, expected orthogonal, eight or chebyshev:<radius>", adjacency),
+    }
+}
+
 fn main() {
-    // Get file name from command line
+    // Get file name (or --diff and two file names) and schematic options from command line
     let args: Vec<String> = env::args().collect();
-    let filename = args.get(1).expect("Please provide a filename");
+    let mut config = SchematicConfig::default();
+    let mut rules: Vec<GearRule> = Vec::new();
+    let mut list_gears = false;
+    let mut edits = Vec::new();
+    let mut regions = Vec::new();
+    let mut nearest = false;
+    let mut render_format = None;
+    let mut diff_mode = false;
+    let mut filenames = Vec::new();
 
-    let file = File::open(filename).expect("Failed to open file");
-    let reader = BufReader::new(file);
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--symbols" => config.symbols = SymbolSet::Only(args.next().expect("Please provide symbols after --symbols").chars().collect()),
+            "--any-symbol" => config.symbols = SymbolSet::AnyExceptBlanks,
+            "--blanks" => config.blanks = args.next().expect("Please provide blanks after --blanks").chars().collect(),
+            "--adjacency" => config.adjacency = parse_adjacency(args.next().expect("Please provide an adjacency after --adjacency")),
+            "--wrap" => config.wrap_parts = true,
+            "--gear" => rules.push(args.next().expect("Please provide a rule after --gear").parse()
+                .unwrap_or_else(|e| panic!("Failed to parse gear rule: {}", e))),
+            "--list-gears" => list_gears = true,
+            "--insert" => edits.push(Edit::Insert(parse_component(args.next().expect("Please provide x,y,value after --insert")))),
+            "--remove" => {
+                let (x, y) = parse_position(args.next().expect("Please provide x,y after --remove"));
+                edits.push(Edit::Remove(x, y));
+            },
+            "--move" => {
+                let (from, to) = args.next().expect("Please provide x,y:x,y after --move").split_once(':').expect("Moves should look like x,y:x,y");
+                edits.push(Edit::Move(parse_position(from), parse_position(to)));
+            },
+            "--region" => {
+                let (from, to) = args.next().expect("Please provide x,y:x,y after --region").split_once(':').expect("Regions should look like x,y:x,y");
+                regions.push((parse_position(from), parse_position(to)));
+            },
+            "--nearest" => nearest = true,
+            "--diff" => diff_mode = true,
+            "--render" => render_format = match args.next().expect("Please provide ansi or html after --render").as_str() {
+                "ansi" => Some(RenderFormat::Ansi),
+                "html" => Some(RenderFormat::Html),
+                format => panic!("Unknown render format {}, expected ansi or html", format),
+            },
+            _ => filenames.push(arg),
+        }
+    }
+    if rules.is_empty() {
+        rules.push(GearRule::default());
+    }
+
+    let fail = |e: SchematicError| -> ! {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    };
+    let load = |filename: &str| {
+        let file = File::open(filename).expect("Failed to open file");
+        Schematic::from_file(BufReader::new(file), config.clone()).unwrap_or_else(|e| {
+            eprintln!("Failed to parse {}: {}", filename, e);
+            std::process::exit(1);
+        })
+    };
+
+    if diff_mode {
+        // Diffs compare the files as they are, so anything acting on a single
+        // schematic would be silently dropped
+        if !edits.is_empty() || !regions.is_empty() || nearest || render_format.is_some() || list_gears {
+            eprintln!("--diff can't be combined with --insert, --remove, --move, --region, --nearest, --render or --list-gears");
+            std::process::exit(1);
+        }
+        let [old, new] = filenames[..] else {
+            panic!("Please provide two filenames after --diff");
+        };
+        print!("{}", diff::diff(&load(old), &load(new), &rules).unwrap_or_else(|e| fail(e)));
+        return;
+    }
+    let filename = filenames.first().expect("Please provide a filename");
+
+    let mut schematic = load(filename);
+    for edit in &edits {
+        apply_edit(&mut schematic.components, edit);
+    }
+
+    for (from, to) in regions {
+        println!("Components from {}, {} to {}, {}:", from.0, from.1, to.0, to.1);
+        for component in schematic.components.components_in(from, to) {
+            println!("  {} at {}, {}", component.component, component.x, component.y);
+        }
+    }
+
+    if nearest {
+        let part_numbers = schematic.get_part_numbers();
+        let mut orphans: Vec<_> = schematic.components.components.iter()
+            .filter(|component| component.component.part_number().is_some() && !part_numbers.contains(component))
+            .collect();
+        orphans.sort_by_key(|part| (part.y, part.x));
+        for part in orphans {
+            match schematic.components.nearest_symbol(part) {
+                Some((symbol, distance)) => println!("Part {} at {}, {} is {} from {} at {}, {}",
+                    part.component, part.x, part.y, distance, symbol.component, symbol.x, symbol.y),
+                None => println!("Part {} at {}, {} has no symbols nearby", part.component, part.x, part.y),
+            }
+        }
+    }
+
+    if let Some(format) = render_format {
+        print!("{}", render::render(&schematic, &rules, format).unwrap_or_else(|e| fail(e)));
+        return;
+    }
 
-    let schematic = Schematic::from_file(reader);
-    
     println!("Result is: {:?}", schematic.get_part_numbers_sum());
 
-    println!("Gear Ratios are: {:?}", schematic.get_gear_ratios());
+    if list_gears {
+        for gear in schematic.get_gears(&rules).unwrap_or_else(|e| fail(e)) {
+            println!("{}", gear);
+        }
+    }
+
+    println!("Gear Ratios are: {:?}", schematic.get_gear_ratios(&rules).unwrap_or_else(|e| fail(e)));
 }
 
 #[cfg(test)]
@@ -287,9 +874,14 @@ mod tests {
         Cursor::new(input.into_bytes())
     }
 
+    // Parse a schematic from a string, shared with the render and diff tests
+    pub fn schematic(input: &str, config: SchematicConfig) -> Schematic {
+        Schematic::from_file(Cursor::new(String::from(input).into_bytes()), config).unwrap()
+    }
+
     #[test]
     fn test_schematic_from_file() {
-        let schematic = Schematic::from_file(test_string());
+        let schematic = Schematic::from_file(test_string(), SchematicConfig::default()).unwrap();
 
         // Assert the expected components
         let expected_components = test_components();
@@ -299,9 +891,9 @@ mod tests {
 
     #[test]
     fn test_get_adjacent_parts() {
-        let schematic = Schematic::from_file(test_string());
+        let schematic = Schematic::from_file(test_string(), SchematicConfig::default()).unwrap();
 
-        let adjacent_parts = schematic.components.get_adjacent_parts(3, 1);
+        let adjacent_parts = schematic.get_adjacent_parts(3, 1);
         println!("Adjacent parts: {:?}", adjacent_parts);
         assert!(adjacent_parts.contains(&SchematicComponent::new(0, 0, ComponentType::Part(467))));
         assert!(adjacent_parts.contains(&SchematicComponent::new(2, 2, ComponentType::Part(35))));
@@ -310,7 +902,7 @@ mod tests {
 
     #[test]
     fn test_get_part_numbers() {
-        let schematic = Schematic::from_file(test_string());
+        let schematic = Schematic::from_file(test_string(), SchematicConfig::default()).unwrap();
 
         let part_numbers = schematic.get_part_numbers();
         println!("Part numbers: {:?}", part_numbers);
@@ -327,7 +919,7 @@ mod tests {
 
     #[test]
     fn test_get_part_numbers_sum() {
-        let schematic = Schematic::from_file(test_string());
+        let schematic = Schematic::from_file(test_string(), SchematicConfig::default()).unwrap();
 
         let part_numbers_sum = schematic.get_part_numbers_sum();
         println!("Part numbers sum: {:?}", part_numbers_sum);
@@ -439,7 +1031,7 @@ mod tests {
         ];
 
         for (input, expected) in tests {
-            let schematic = Schematic::from_file(Cursor::new(String::from(input).into_bytes()));
+            let schematic = schematic(input, SchematicConfig::default());
 
             let part_numbers_sum = schematic.get_part_numbers_sum();
             assert_eq!(part_numbers_sum, expected);
@@ -447,17 +1039,240 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
     fn test_get_part_numbers_sum_unknown_symbol() {
-        Schematic::from_file(Cursor::new(String::from("?").into_bytes()));
+        let result = Schematic::from_file(Cursor::new(String::from("?").into_bytes()), SchematicConfig::default());
+        assert_eq!(result.unwrap_err(), SchematicError::UnknownSymbol { position: (0, 0), symbol: '?' });
+    }
+
+    #[test]
+    fn test_config_symbols_and_blanks() {
+        let config = SchematicConfig {
+            symbols: SymbolSet::Only(HashSet::from(['★', '?'])),
+            blanks: HashSet::from(['·', ' ']),
+            ..SchematicConfig::default()
+        };
+        let input = "12·★7\n··?··3";
+        let only = schematic(input, config);
+
+        assert!(only.components.components.contains(&SchematicComponent::new(3, 0, ComponentType::Symbol('★'))));
+        assert!(only.components.components.contains(&SchematicComponent::new(4, 0, ComponentType::Part(7))));
+        assert_eq!(only.get_part_numbers_sum(), 12 + 7);
+
+        let config = SchematicConfig {
+            symbols: SymbolSet::AnyExceptBlanks,
+            ..SchematicConfig::default()
+        };
+        assert_eq!(schematic("1?.~2", config).get_part_numbers_sum(), 3);
+    }
+
+    #[test]
+    fn test_config_adjacency() {
+        let input = "1.2\n.+.\n..3\n..4";
+        let sum_with = |adjacency| {
+            let config = SchematicConfig { adjacency, ..SchematicConfig::default() };
+            schematic(input, config).get_part_numbers_sum()
+        };
+
+        assert_eq!(sum_with(Adjacency::Orthogonal), 0);
+        assert_eq!(sum_with(Adjacency::EightWay), 1 + 2 + 3);
+        assert_eq!(sum_with(Adjacency::Chebyshev(2)), 1 + 2 + 3 + 4);
+        assert_eq!(Adjacency::Chebyshev(1).neighbours(0, 0), Adjacency::EightWay.neighbours(0, 0));
+    }
+
+    #[test]
+    fn test_config_wrap_parts() {
+        let input = "...12\n3+...";
+        let config = SchematicConfig { wrap_parts: true, ..SchematicConfig::default() };
+        let wrapped = schematic(input, config);
+
+        let part = SchematicComponent::new(3, 0, ComponentType::Part(123));
+        assert_eq!(wrapped.components.components[0], part);
+        assert_eq!(wrapped.components.coord_index.get(&(0, 1)), Some(&part));
+        assert_eq!(wrapped.get_part_numbers_sum(), 123);
+
+        // Without wrapping, 12 is too far from the symbol
+        assert_eq!(schematic(input, SchematicConfig::default()).get_part_numbers_sum(), 3);
+    }
+
+    #[test]
+    fn test_schematic_errors() {
+        let config = SchematicConfig { wrap_parts: true, ..SchematicConfig::default() };
+        let result = Schematic::from_file(Cursor::new(String::from("...12\n3+").into_bytes()), config);
+        assert_eq!(result.unwrap_err(), SchematicError::RowWidth { row: 1, width: 2, expected: 5 });
+
+        let digits = "9".repeat(40);
+        let result = Schematic::from_file(Cursor::new(format!(".{}", digits).into_bytes()), SchematicConfig::default());
+        assert_eq!(result.unwrap_err(), SchematicError::BadNumber { position: (1, 0), digits });
+
+        let existing = SchematicComponent::new(0, 0, ComponentType::Part(12));
+        let mut components = IndexedComponentList::new();
+        let result = components.set_component(vec![existing, SchematicComponent::new(1, 0, ComponentType::Symbol('*'))]);
+        assert_eq!(result, Err(EditError::Overlap { position: (1, 0), existing }));
     }
 
     #[test]
     fn test_get_gear_ratios() {
-        let schematic = Schematic::from_file(test_string());
+        let schematic = Schematic::from_file(test_string(), SchematicConfig::default()).unwrap();
 
-        let gear_ratios = schematic.get_gear_ratios();
+        let gear_ratios = schematic.get_gear_ratios(&[GearRule::default()]).unwrap();
         println!("Gear ratios: {:?}", gear_ratios);
         assert_eq!(gear_ratios, 467835);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_get_gears() {
+        let schematic = Schematic::from_file(test_string(), SchematicConfig::default()).unwrap();
+
+        let gears = schematic.get_gears(&[GearRule::default()]).unwrap();
+        assert_eq!(gears.len(), 2);
+        assert_eq!(gears[0].symbol, SchematicComponent::new(3, 1, ComponentType::Symbol('*')));
+        assert_eq!(gears[0].parts, vec![
+            SchematicComponent::new(0, 0, ComponentType::Part(467)),
+            SchematicComponent::new(2, 2, ComponentType::Part(35)),
+        ]);
+        assert_eq!(gears[0].ratio, 16345);
+        assert_eq!(gears[0].to_string(), "* at (3, 1): 467 x 35 = 16345");
+        assert_eq!(gears[1].ratio, 451490);
+    }
+
+    #[test]
+    fn test_get_gears_multiple_rules() {
+        let schematic = Schematic::from_file(test_string(), SchematicConfig::default()).unwrap();
+
+        let rules = [
+            "*:>=1:sum".parse().unwrap(),
+            "#:1:max".parse().unwrap(),
+            "+:<=0:product".parse().unwrap(),
+        ];
+        let gears = schematic.get_gears(&rules).unwrap();
+        let ratios: Vec<_> = gears.iter().map(|gear| (gear.symbol.component, gear.ratio)).collect();
+        assert_eq!(ratios, vec![
+            (ComponentType::Symbol('*'), 467 + 35),
+            (ComponentType::Symbol('#'), 633),
+            (ComponentType::Symbol('*'), 617),
+            (ComponentType::Symbol('*'), 755 + 598),
+        ]);
+    }
+
+    #[test]
+    fn test_get_gears_needs_parts() {
+        // An isolated symbol has no ratio, even when the rule allows no parts
+        let rules = ["*:<=2:product".parse().unwrap()];
+        let gears = schematic("*...\n..*3", SchematicConfig::default()).get_gears(&rules).unwrap();
+        assert_eq!(gears.len(), 1);
+        assert_eq!(gears[0].ratio, 3);
+    }
+
+    #[test]
+    fn test_gear_ratio_overflow() {
+        let big = "9".repeat(11);
+        let input = format!("{}*{}", big, big);
+        let wide = schematic(&input, SchematicConfig::default());
+
+        assert_eq!(wide.get_gears(&[GearRule::default()]), Err(SchematicError::RatioOverflow { position: (11, 0) }));
+        assert_eq!(wide.get_gear_ratios(&["*:2:sum".parse().unwrap()]), Ok(2 * 99_999_999_999));
+
+        // Each ratio fits but their total doesn't
+        let big = "1".repeat(20);
+        let input = format!("{}*\n{}\n{}*", big, ".".repeat(21), big);
+        let wide = schematic(&input, SchematicConfig::default());
+        assert_eq!(wide.get_gears(&["*:1:max".parse().unwrap()]).map(|gears| gears.len()), Ok(2));
+        assert_eq!(wide.get_gear_ratios(&["*:1:max".parse().unwrap()]), Err(SchematicError::RatioOverflow { position: (20, 2) }));
+    }
+
+    #[test]
+    fn test_gear_rule_from_str() {
+        assert_eq!("*:2:product".parse(), Ok(GearRule::default()));
+        assert_eq!("*:=2:product".parse(), Ok(GearRule::default()));
+        assert_eq!("@:>=3:sum".parse(), Ok(GearRule { symbol: '@', parts: PartCount::Min(3), combine: Combine::Sum }));
+        assert_eq!("@:<=1:max".parse(), Ok(GearRule { symbol: '@', parts: PartCount::Max(1), combine: Combine::Max }));
+        assert!("*:2".parse::<GearRule>().is_err());
+        assert!("**:2:sum".parse::<GearRule>().is_err());
+        assert!("*:two:sum".parse::<GearRule>().is_err());
+        assert!("*:2:mean".parse::<GearRule>().is_err());
+    }
+
+    #[test]
+    fn test_insert_component() {
+        let mut schematic = Schematic::from_file(test_string(), SchematicConfig::default()).unwrap();
+
+        let part = SchematicComponent::new(8, 0, ComponentType::Part(42));
+        assert_eq!(schematic.components.insert(part), Ok(()));
+        assert_eq!(schematic.components.coord_index.get(&(9, 0)), Some(&part));
+        assert_eq!(schematic.components.type_index.get(&ComponentType::Part(42)), Some(&vec![part]));
+
+        let overlapping = SchematicComponent::new(4, 0, ComponentType::Part(10));
+        assert_eq!(schematic.components.insert(overlapping), Err(EditError::Overlap {
+            position: (5, 0),
+            existing: SchematicComponent::new(5, 0, ComponentType::Part(114)),
+        }));
+        assert!(!schematic.components.components.contains(&overlapping));
+        assert_eq!(schematic.components.coord_index.get(&(4, 0)), None);
+    }
+
+    #[test]
+    fn test_remove_component() {
+        let mut schematic = Schematic::from_file(test_string(), SchematicConfig::default()).unwrap();
+
+        let symbol = SchematicComponent::new(6, 3, ComponentType::Symbol('#'));
+        assert_eq!(schematic.components.remove(&symbol), Ok(symbol));
+        assert_eq!(schematic.components.coord_index.get(&(6, 3)), None);
+        assert_eq!(schematic.components.type_index.get(&symbol.component), None);
+        assert_eq!(schematic.get_part_numbers_sum(), 4361 - 633);
+
+        assert_eq!(schematic.components.remove(&symbol), Err(EditError::NotFound(symbol)));
+
+        // The last component took the removed one's place, and can still be found
+        let last = SchematicComponent::new(5, 9, ComponentType::Part(598));
+        assert_eq!(schematic.components.components[5], last);
+        assert_eq!(schematic.components.remove(&last), Ok(last));
+        for (index, component) in schematic.components.components.iter().enumerate() {
+            assert_eq!(schematic.components.list_index[&(component.x, component.y)], index);
+        }
+        assert_eq!(schematic.components.list_index.len(), schematic.components.components.len());
+    }
+
+    #[test]
+    fn test_move_component() {
+        let mut schematic = Schematic::from_file(test_string(), SchematicConfig::default()).unwrap();
+
+        let part = SchematicComponent::new(5, 0, ComponentType::Part(114));
+        let moved = SchematicComponent::new(4, 0, ComponentType::Part(114));
+        // Moving onto itself is fine
+        assert_eq!(schematic.components.move_component(&part, 4, 0), Ok(moved));
+        assert_eq!(schematic.components.coord_index.get(&(4, 0)), Some(&moved));
+        assert_eq!(schematic.components.coord_index.get(&(7, 0)), None);
+        assert_eq!(schematic.components.components[1], moved);
+        assert_eq!(schematic.components.list_index.get(&(4, 0)), Some(&1));
+        assert_eq!(schematic.components.list_index.get(&(5, 0)), None);
+        assert_eq!(schematic.get_part_numbers_sum(), 4361 + 114);
+
+        assert!(matches!(schematic.components.move_component(&moved, 1, 0), Err(EditError::Overlap { .. })));
+        assert_eq!(schematic.components.move_component(&part, 0, 3), Err(EditError::NotFound(part)));
+    }
+
+    #[test]
+    fn test_components_in() {
+        let schematic = Schematic::from_file(test_string(), SchematicConfig::default()).unwrap();
+
+        assert_eq!(schematic.components.components_in((2, 0), (3, 2)), vec![
+            SchematicComponent::new(0, 0, ComponentType::Part(467)),
+            SchematicComponent::new(3, 1, ComponentType::Symbol('*')),
+            SchematicComponent::new(2, 2, ComponentType::Part(35)),
+        ]);
+        assert_eq!(schematic.components.components_in((0, 0), (100, 100)).len(), 16);
+        assert_eq!(schematic.components.components_in((8, 8), (9, 9)), vec![]);
+    }
+
+    #[test]
+    fn test_nearest_symbol() {
+        let schematic = Schematic::from_file(test_string(), SchematicConfig::default()).unwrap();
+
+        let part = SchematicComponent::new(5, 0, ComponentType::Part(114));
+        assert_eq!(schematic.components.nearest_symbol(&part), Some((SchematicComponent::new(3, 1, ComponentType::Symbol('*')), 2)));
+
+        // Both '#' and '+' are two away, so the earlier one wins
+        let part = SchematicComponent::new(7, 5, ComponentType::Part(58));
+        assert_eq!(schematic.components.nearest_symbol(&part), Some((SchematicComponent::new(6, 3, ComponentType::Symbol('#')), 2)));
+    }
+}
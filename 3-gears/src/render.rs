@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+use crate::{ComponentType, GearRule, Schematic, SchematicError, X, Y};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderFormat {
+    Ansi,
+    Html,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Class {
+    Blank,
+    Part,
+    Orphan,
+    Symbol,
+    Gear,
+}
+
+impl Class {
+    fn ansi(&self) -> &'static str {
+        match self {
+            Class::Blank => "\x1b[2m",
+            Class::Part => "\x1b[32m",
+            Class::Orphan => "\x1b[31m",
+            Class::Symbol => "\x1b[33m",
+            Class::Gear => "\x1b[1;35m",
+        }
+    }
+
+    fn html(&self) -> &'static str {
+        match self {
+            Class::Blank => "blank",
+            Class::Part => "part",
+            Class::Orphan => "orphan",
+            Class::Symbol => "symbol",
+            Class::Gear => "gear",
+        }
+    }
+}
+
+const ANSI_RESET: &str = "\x1b[0m";
+
+const HTML_STYLE: &str = "<style>
+.schematic .blank { color: #999; }
+.schematic .part { color: #2a2; }
+.schematic .orphan { color: #c22; }
+.schematic .symbol { color: #b80; }
+.schematic .gear { color: #a2a; font-weight: bold; }
+</style>";
+
+fn paint(text: &str, class: Class, format: RenderFormat) -> String {
+    match format {
+        RenderFormat::Ansi => format!("{}{}{}", class.ansi(), text, ANSI_RESET),
+        RenderFormat::Html => format!("<span class=\"{}\">{}</span>", class.html(), escape_html(text)),
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+// Reprint the schematic with part numbers coloured by whether they touch a
+// symbol, gears highlighted and their ratios noted at the end of each row,
+// followed by a legend and totals.
+pub fn render(schematic: &Schematic, rules: &[GearRule], format: RenderFormat) -> Result<String, SchematicError> {
+    let components = &schematic.components;
+    let part_numbers = schematic.get_part_numbers();
+    let gears = schematic.get_gears(rules)?;
+
+    let blank = if schematic.config.blanks.contains(&'.') {
+        '.'
+    } else {
+        schematic.config.blanks.iter().min().copied().unwrap_or(' ')
+    };
+
+    let mut cells: HashMap<(X, Y), (char, Class)> = HashMap::new();
+    for component in &components.components {
+        let class = match component.component {
+            ComponentType::Part(_) if part_numbers.contains(component) => Class::Part,
+            ComponentType::Part(_) => Class::Orphan,
+            ComponentType::Symbol(_) if gears.iter().any(|gear| gear.symbol == *component) => Class::Gear,
+            ComponentType::Symbol(_) => Class::Symbol,
+        };
+        let text = component.component.to_string();
+        for (position, character) in components.cells(component).into_iter().zip(text.chars()) {
+            cells.insert(position, (character, class));
+        }
+    }
+
+    // Edits may have placed components outside the original rows
+    let width = cells.keys().map(|(x, _)| x + 1).fold(schematic.size.0, X::max);
+    let height = cells.keys().map(|(_, y)| y + 1).fold(schematic.size.1, Y::max);
+
+    let mut output = String::new();
+    if format == RenderFormat::Html {
+        output.push_str(HTML_STYLE);
+        output.push_str("\n<pre class=\"schematic\">\n");
+    }
+
+    for y in 0..height {
+        // Group runs of the same class so each gets a single colour change
+        let mut runs: Vec<(String, Class)> = Vec::new();
+        for x in 0..width {
+            let (character, class) = cells.get(&(x, y)).copied().unwrap_or((blank, Class::Blank));
+            match runs.last_mut() {
+                Some((text, last_class)) if *last_class == class => text.push(character),
+                _ => runs.push((character.to_string(), class)),
+            }
+        }
+        for (text, class) in runs {
+            output.push_str(&paint(&text, class, format));
+        }
+
+        let row_gears: Vec<String> = gears.iter()
+            .filter(|gear| gear.symbol.y == y)
+            .map(|gear| gear.to_string())
+            .collect();
+        if !row_gears.is_empty() {
+            output.push_str(&paint(&format!("  {}", row_gears.join("; ")), Class::Gear, format));
+        }
+        output.push('\n');
+    }
+
+    if format == RenderFormat::Html {
+        output.push_str("</pre>\n<pre class=\"schematic\">\n");
+    } else {
+        output.push('\n');
+    }
+
+    output.push_str(&format!(
+        "Legend: {} {} {} {}\n",
+        paint("part number", Class::Part, format),
+        paint("orphan number", Class::Orphan, format),
+        paint("symbol", Class::Symbol, format),
+        paint("gear", Class::Gear, format),
+    ));
+
+    let (parts, orphans): (Vec<_>, Vec<_>) = components.components.iter()
+        .filter_map(|component| component.component.part_number().map(|part_number| (component, part_number)))
+        .partition(|(component, _)| part_numbers.contains(component));
+    output.push_str(&format!("Part numbers: {} (sum {})\n", parts.len(), parts.iter().map(|(_, n)| n).sum::<usize>()));
+    output.push_str(&format!("Orphan numbers: {} (sum {})\n", orphans.len(), orphans.iter().map(|(_, n)| n).sum::<usize>()));
+    output.push_str(&format!("Gears: {} (ratio total {})\n", gears.len(), schematic.get_gear_ratios(rules)?));
+
+    if format == RenderFormat::Html {
+        output.push_str("</pre>\n");
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SchematicConfig, SymbolSet};
+
+    fn schematic(input: &str) -> Schematic {
+        let config = SchematicConfig {
+            symbols: SymbolSet::AnyExceptBlanks,
+            ..SchematicConfig::default()
+        };
+        crate::tests::schematic(input, config)
+    }
+
+    #[test]
+    fn test_render_ansi() {
+        let schematic = schematic("467..114..\n...*......\n..35...<>.");
+        let rendered = render(&schematic, &[GearRule::default()], RenderFormat::Ansi).unwrap();
+
+        let expected = "\
+\x1b[32m467\x1b[0m\x1b[2m..\x1b[0m\x1b[31m114\x1b[0m\x1b[2m..\x1b[0m
+\x1b[2m...\x1b[0m\x1b[1;35m*\x1b[0m\x1b[2m......\x1b[0m\x1b[1;35m  * at (3, 1): 467 x 35 = 16345\x1b[0m
+\x1b[2m..\x1b[0m\x1b[32m35\x1b[0m\x1b[2m...\x1b[0m\x1b[33m<>\x1b[0m\x1b[2m.\x1b[0m
+
+Legend: \x1b[32mpart number\x1b[0m \x1b[31morphan number\x1b[0m \x1b[33msymbol\x1b[0m \x1b[1;35mgear\x1b[0m
+Part numbers: 2 (sum 502)
+Orphan numbers: 1 (sum 114)
+Gears: 1 (ratio total 16345)
+";
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn test_render_html() {
+        let schematic = schematic("1.2\n<..");
+        let rendered = render(&schematic, &[GearRule::default()], RenderFormat::Html).unwrap();
+
+        let expected = format!("{}
+<pre class=\"schematic\">
+<span class=\"part\">1</span><span class=\"blank\">.</span><span class=\"orphan\">2</span>
+<span class=\"symbol\">&lt;</span><span class=\"blank\">..</span>
+</pre>
+<pre class=\"schematic\">
+Legend: <span class=\"part\">part number</span> <span class=\"orphan\">orphan number</span> <span class=\"symbol\">symbol</span> <span class=\"gear\">gear</span>
+Part numbers: 1 (sum 1)
+Orphan numbers: 1 (sum 2)
+Gears: 0 (ratio total 0)
+</pre>
+", HTML_STYLE);
+        assert_eq!(rendered, expected);
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::HashSet;
+
+use rand::Rng;
+
+use crate::stream::{StreamError, StreamingCardStack};
+use crate::Card;
+
+// Shape of randomly generated cards: how many winning and scratch numbers
+// each has, all distinct within their set and drawn from min..=max
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CardGenerator {
+    pub winning: usize,
+    pub scratch: usize,
+    pub min: usize,
+    pub max: usize,
+}
+
+impl CardGenerator {
+    // How many numbers are in min..=max, or None if that doesn't fit in a usize
+    fn range_size(&self) -> Option<usize> {
+        self.max.checked_sub(self.min)?.checked_add(1)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min > self.max {
+            return Err(format!("Number range {}..={} is empty", self.min, self.max));
+        }
+        let Some(range_size) = self.range_size() else {
+            return Err(format!("Number range {}..={} is too large", self.min, self.max));
+        };
+        if self.winning > range_size || self.scratch > range_size {
+            return Err(format!(
+                "Cannot draw {} winning and {} scratch numbers from the {} numbers in {}..={}",
+                self.winning, self.scratch, range_size, self.min, self.max
+            ));
+        }
+        Ok(())
+    }
+
+    // Pick `count` distinct numbers with Floyd's algorithm, so only the chosen
+    // numbers are held rather than the whole range
+    fn distinct_numbers(&self, count: usize, rng: &mut impl Rng) -> Vec<usize> {
+        let range_size = self.range_size().expect("Generator should be validated first");
+        let mut chosen = HashSet::with_capacity(count);
+        let mut numbers = Vec::with_capacity(count);
+
+        for last in range_size - count..range_size {
+            let offset = rng.gen_range(0..=last);
+            let offset = if chosen.insert(offset) { offset } else {
+                chosen.insert(last);
+                last
+            };
+            numbers.push(self.min + offset);
+        }
+
+        numbers
+    }
+
+    pub fn generate(&self, id: usize, rng: &mut impl Rng) -> Card {
+        Card {
+            id,
+            winning_numbers: self.distinct_numbers(self.winning, rng),
+            scratch_numbers: self.distinct_numbers(self.scratch, rng),
+        }
+    }
+
+    // Probability of each match count, from the hypergeometric distribution:
+    // the scratch numbers are a sample without replacement from the range, of
+    // which the winning numbers are the successes
+    pub fn match_distribution(&self) -> Vec<f64> {
+        let n = self.range_size().expect("Generator should be validated first");
+        let ln_total = ln_choose(n, self.scratch);
+
+        (0..=self.winning.min(self.scratch)).map(|k| {
+            if self.scratch - k > n - self.winning {
+                return 0.0;
+            }
+            (ln_choose(self.winning, k) + ln_choose(n - self.winning, self.scratch - k) - ln_total).exp()
+        }).collect()
+    }
+
+    // Expected points from calculate_winnings for one card
+    pub fn expected_points(&self) -> f64 {
+        self.match_distribution().iter().enumerate()
+            .skip(1)
+            .map(|(matches, probability)| probability * 2f64.powi(matches as i32 - 1))
+            .sum()
+    }
+
+    // Expected total number of cards (originals plus copies) in a game of
+    // `cards` cards. A card's match count is independent of how many copies of
+    // it were won, so E[copies of i] = 1 + sum of E[copies of j] * P(matches of j >= i - j).
+    pub fn expected_copies(&self, cards: usize) -> f64 {
+        let distribution = self.match_distribution();
+        // at_least[d] = P(matches >= d)
+        let mut at_least = vec![0.0; distribution.len() + 1];
+        for d in (0..distribution.len()).rev() {
+            at_least[d] = at_least[d + 1] + distribution[d];
+        }
+
+        let mut copies: Vec<f64> = Vec::with_capacity(cards);
+        for i in 0..cards {
+            let won: f64 = (1..at_least.len()).take(i)
+                .map(|distance| copies[i - distance] * at_least[distance])
+                .sum();
+            copies.push(1.0 + won);
+        }
+
+        copies.iter().sum()
+    }
+
+    // Play `trials` random games of `cards` cards, returning the mean and
+    // standard error of the total points and of the total number of cards
+    pub fn simulate(&self, cards: usize, trials: usize, rng: &mut impl Rng) -> Result<(Estimate, Estimate), StreamError> {
+        // With no games the estimates would be 0 / 0
+        assert!(trials > 0, "Need at least one trial to simulate");
+        let mut points = Vec::with_capacity(trials);
+        let mut copies = Vec::with_capacity(trials);
+
+        for _ in 0..trials {
+            let mut stream = StreamingCardStack::new();
+            for id in 1..=cards {
+                stream.add_card(&self.generate(id, rng))?;
+            }
+            points.push(stream.total_winnings() as f64);
+            copies.push(stream.count_copies() as f64);
+        }
+
+        Ok((Estimate::from_samples(&points), Estimate::from_samples(&copies)))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Estimate {
+    pub mean: f64,
+    pub standard_error: f64,
+}
+
+impl Estimate {
+    fn from_samples(samples: &[f64]) -> Estimate {
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let variance = samples.iter().map(|sample| (sample - mean).powi(2)).sum::<f64>() / (n - 1.0).max(1.0);
+        Estimate { mean, standard_error: (variance / n).sqrt() }
+    }
+}
+
+fn ln_choose(n: usize, k: usize) -> f64 {
+    if k > n {
+        return f64::NEG_INFINITY;
+    }
+    let k = k.min(n - k);
+    (0..k).map(|i| ((n - i) as f64).ln() - ((i + 1) as f64).ln()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    // Numbers shared by a card's winning and scratch sets, for checking the generator
+    fn overlap(card: &Card) -> usize {
+        let winning: HashSet<&usize> = card.winning_numbers.iter().collect();
+        card.scratch_numbers.iter().filter(|n| winning.contains(n)).count()
+    }
+
+    #[test]
+    fn test_generate() {
+        let generator = CardGenerator { winning: 5, scratch: 8, min: 1, max: 20 };
+        let mut rng = StdRng::seed_from_u64(7);
+
+        for id in 1..=50 {
+            let card = generator.generate(id, &mut rng);
+            assert_eq!(card.id, id);
+            assert_eq!(card.winning_numbers.len(), 5);
+            assert_eq!(card.scratch_numbers.len(), 8);
+            assert_eq!(card.winning_numbers.iter().collect::<HashSet<_>>().len(), 5);
+            assert_eq!(card.scratch_numbers.iter().collect::<HashSet<_>>().len(), 8);
+            assert!(card.scratch_numbers.iter().all(|n| (1..=20).contains(n)));
+            assert_eq!(overlap(&card), card.number_of_winning_cards());
+        }
+
+        assert!(CardGenerator { winning: 5, scratch: 30, min: 1, max: 20 }.validate().is_err());
+        assert!(CardGenerator { winning: 1, scratch: 1, min: 2, max: 1 }.validate().is_err());
+        assert!(CardGenerator { winning: 1, scratch: 1, min: 0, max: usize::MAX }.validate().is_err());
+
+        // Drawing from a huge range only holds the numbers drawn
+        let huge = CardGenerator { winning: 3, scratch: 3, min: 1, max: usize::MAX };
+        let card = huge.generate(1, &mut rng);
+        assert_eq!(card.winning_numbers.iter().collect::<HashSet<_>>().len(), 3);
+
+        // Drawing every number gives each exactly once
+        let all = CardGenerator { winning: 20, scratch: 20, min: 1, max: 20 };
+        let mut numbers = all.generate(1, &mut rng).winning_numbers;
+        numbers.sort();
+        assert_eq!(numbers, (1..=20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_match_distribution() {
+        let generator = CardGenerator { winning: 2, scratch: 2, min: 1, max: 4 };
+        let distribution = generator.match_distribution();
+
+        let expected = [1.0 / 6.0, 4.0 / 6.0, 1.0 / 6.0];
+        for (probability, expected) in distribution.iter().zip(expected) {
+            assert!((probability - expected).abs() < 1e-12);
+        }
+        assert!((generator.expected_points() - 1.0).abs() < 1e-12);
+
+        let generator = CardGenerator { winning: 10, scratch: 25, min: 1, max: 99 };
+        assert!((generator.match_distribution().iter().sum::<f64>() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_expected_copies() {
+        // Every card matches its single winning number, so card i has i copies
+        let generator = CardGenerator { winning: 1, scratch: 3, min: 1, max: 3 };
+        assert!((generator.expected_copies(3) - 6.0).abs() < 1e-12);
+
+        // No card can match anything
+        let generator = CardGenerator { winning: 2, scratch: 2, min: 1, max: 4 };
+        let never = CardGenerator { scratch: 0, ..generator };
+        assert!((never.expected_copies(10) - 10.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_simulation_agrees_with_exact() {
+        let generator = CardGenerator { winning: 5, scratch: 8, min: 1, max: 30 };
+        let cards = 12;
+        let mut rng = StdRng::seed_from_u64(2023);
+
+        let (points, copies) = generator.simulate(cards, 4000, &mut rng).expect("Simulation overflowed");
+
+        let exact_points = generator.expected_points() * cards as f64;
+        let exact_copies = generator.expected_copies(cards);
+        assert!((points.mean - exact_points).abs() < 4.0 * points.standard_error);
+        assert!((copies.mean - exact_copies).abs() < 4.0 * copies.standard_error);
+    }
+}
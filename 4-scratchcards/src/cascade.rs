@@ -0,0 +1,92 @@
+// One card in the copy cascade: how many numbers it matched, how many
+// instances of it ended up in the stack, and which earlier cards gave copies
+#[derive(Debug, PartialEq)]
+pub struct CascadeEntry {
+    pub id: usize,
+    pub matches: usize,
+    pub copies: usize,
+    pub sources: Vec<(usize, usize)>,
+}
+
+impl CascadeEntry {
+    // Copies won from earlier cards, excluding the original
+    fn received(&self) -> usize {
+        self.sources.iter().map(|(_, copies)| copies).sum()
+    }
+}
+
+pub fn render_table(entries: &[CascadeEntry]) -> String {
+    let mut table = String::from("Card | Matches | Copies | Received | From\n");
+
+    for entry in entries {
+        let sources = entry.sources.iter()
+            .map(|(id, copies)| format!("{} (x{})", id, copies))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let row = format!(
+            "{:>4} | {:>7} | {:>6} | {:>8} | {}",
+            entry.id, entry.matches, entry.copies, entry.received(), sources
+        );
+        table.push_str(row.trim_end());
+        table.push('\n');
+    }
+
+    table
+}
+
+pub fn render_dot(entries: &[CascadeEntry]) -> String {
+    let mut graph = String::from("digraph cascade {\n    rankdir=LR;\n");
+
+    for entry in entries {
+        graph.push_str(&format!(
+            "    card{} [label=\"Card {}\\n{} matches, {} copies\"];\n",
+            entry.id, entry.id, entry.matches, entry.copies
+        ));
+    }
+    for entry in entries {
+        for (source, copies) in &entry.sources {
+            graph.push_str(&format!("    card{} -> card{} [label=\"{}\"];\n", source, entry.id, copies));
+        }
+    }
+
+    graph.push_str("}\n");
+    graph
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries() -> Vec<CascadeEntry> {
+        vec![
+            CascadeEntry { id: 1, matches: 2, copies: 1, sources: vec![] },
+            CascadeEntry { id: 2, matches: 1, copies: 2, sources: vec![(1, 1)] },
+            CascadeEntry { id: 3, matches: 0, copies: 4, sources: vec![(1, 1), (2, 2)] },
+        ]
+    }
+
+    #[test]
+    fn test_render_table() {
+        assert_eq!(render_table(&entries()), "\
+Card | Matches | Copies | Received | From
+   1 |       2 |      1 |        0 |
+   2 |       1 |      2 |        1 | 1 (x1)
+   3 |       0 |      4 |        3 | 1 (x1), 2 (x2)
+");
+    }
+
+    #[test]
+    fn test_render_dot() {
+        assert_eq!(render_dot(&entries()), "\
+digraph cascade {
+    rankdir=LR;
+    card1 [label=\"Card 1\\n2 matches, 1 copies\"];
+    card2 [label=\"Card 2\\n1 matches, 2 copies\"];
+    card3 [label=\"Card 3\\n0 matches, 4 copies\"];
+    card1 -> card2 [label=\"1\"];
+    card1 -> card3 [label=\"1\"];
+    card2 -> card3 [label=\"2\"];
+}
+");
+    }
+}
@@ -1,9 +1,17 @@
+mod analysis;
+mod cascade;
+mod stream;
+
 use std::collections::{HashSet, HashMap};
-use std::{env, fs::File};
+use std::{env, fmt, fs::File};
 use std::io::{BufRead, BufReader};
 
-const WINNING_SIZE: usize = 10;
-const SCRATCH_SIZE: usize = 25;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use analysis::CardGenerator;
+use cascade::CascadeEntry;
+use stream::StreamingCardStack;
 
 struct CardStack {
     cards: HashMap<usize, CopiedCard>,
@@ -12,13 +20,31 @@ struct CardStack {
 struct CopiedCard {
     copies: usize,
     card: Option<Card>,
+    // Earlier cards that won copies of this one, and how many each gave
+    sources: Vec<(usize, usize)>,
 }
 
 #[derive(Debug, PartialEq)]
 struct Card {
     id: usize,
-    winning_numbers: [usize; WINNING_SIZE],
-    scratch_numbers: [usize; SCRATCH_SIZE],
+    winning_numbers: Vec<usize>,
+    scratch_numbers: Vec<usize>,
+}
+
+#[derive(Debug, PartialEq)]
+enum CardError {
+    Duplicate(usize),
+    // Copy counts or points no longer fit in a usize
+    Overflow(usize),
+}
+
+impl fmt::Display for CardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CardError::Duplicate(id) => write!(f, "Card {} appears more than once", id),
+            CardError::Overflow(id) => write!(f, "Card {}: copy count or winnings overflowed", id),
+        }
+    }
 }
 
 impl CardStack {
@@ -28,23 +54,24 @@ impl CardStack {
         }
     }
 
-    fn add_card(&mut self, card: Card) {
+    fn add_card(&mut self, card: Card) -> Result<(), CardError> {
         let id = card.id;
         let number_of_winning_cards = card.number_of_winning_cards();
 
-        // panic if the CopiedCard already exists with Some card
+        // Error if the CopiedCard already exists with Some card
         if let Some(copied_card) = self.cards.get_mut(&id) {
             if copied_card.card.is_some() {
-                panic!("Card already exists");
+                return Err(CardError::Duplicate(id));
             }
 
+            copied_card.copies = copied_card.copies.checked_add(1).ok_or(CardError::Overflow(id))?;
             copied_card.card = Some(card);
-            copied_card.copies += 1;
         } else {
             let card = CopiedCard {
                 // Add one to existing
                 copies: 1,
                 card: Some(card),
+                sources: Vec::new(),
             };
             self.cards.insert(id, card);
         }
@@ -52,29 +79,56 @@ impl CardStack {
             Some(card) => card.copies,
             None => 1,
         };
-        self.recieve_copies(id, multipler, number_of_winning_cards);
+        self.recieve_copies(id, multipler, number_of_winning_cards)
     }
 
-    fn recieve_copies(&mut self, id: usize, multiplier: usize, new_cards: usize) {
+    fn recieve_copies(&mut self, id: usize, multiplier: usize, new_cards: usize) -> Result<(), CardError> {
         let iter = id+1..id+new_cards+1;
         for i in iter {
             match self.cards.get_mut(&i) {
-                Some(card) => card.copies += multiplier,
+                Some(card) => {
+                    card.copies = card.copies.checked_add(multiplier).ok_or(CardError::Overflow(i))?;
+                    card.sources.push((id, multiplier));
+                },
                 None => {
                     let card = CopiedCard {
                         copies: multiplier,
                         card: None,
+                        sources: vec![(id, multiplier)],
                     };
                     self.cards.insert(i, card);
                 }
             }
         }
+        Ok(())
     }
 
-    fn count_copies(&self) -> usize {
+    fn count_copies(&self) -> Result<usize, CardError> {
+        self.cards.iter()
+            .filter(|(_, card)| card.card.is_some())
+            .try_fold(0usize, |total, (id, card)| total.checked_add(card.copies).ok_or(CardError::Overflow(*id)))
+    }
+
+    // Per-card view of how copies flowed through the stack, ordered by card id
+    fn cascade(&self) -> Vec<CascadeEntry> {
+        let mut entries: Vec<CascadeEntry> = self.cards.iter()
+            .filter_map(|(id, copied)| copied.card.as_ref().map(|card| CascadeEntry {
+                id: *id,
+                matches: card.number_of_winning_cards(),
+                copies: copied.copies,
+                sources: copied.sources.clone(),
+            }))
+            .collect();
+
+        entries.sort_by_key(|entry| entry.id);
+        entries
+    }
+
+    // Points from scoring each original card once, ignoring copies
+    fn total_winnings(&self) -> Result<usize, CardError> {
         self.cards.values()
-            .map(|card| if card.card.is_some() { card.copies } else { 0 })
-            .sum()
+            .filter_map(|card| card.card.as_ref())
+            .try_fold(0usize, |total, card| total.checked_add(card.calculate_winnings()?).ok_or(CardError::Overflow(card.id)))
     }
 }
 
@@ -82,33 +136,33 @@ impl TryFrom<String> for Card {
     type Error = String;
 
     fn try_from(line: String) -> Result<Self, Self::Error> {
-        let mut parts = line.split_whitespace();
-        let id_str = parts
+        let (header, numbers) = line.split_once(':')
+            .ok_or_else(|| format!("Missing ':' after card id in '{}'", line))?;
+
+        let id_str = header.split_whitespace()
             .nth(1)
             .ok_or_else(|| "Missing card id".to_string())?;
 
-        let mut id_chars = id_str.chars();
-        id_chars.next_back(); // Remove the colon
-
-        let id = id_chars.as_str()
+        let id = id_str
             .parse::<usize>()
-            .expect("Unable to parse card id");
+            .map_err(|_| format!("Unable to parse card id: {}", id_str))?;
 
-        let winning_numbers: [usize; WINNING_SIZE] = parts.clone()
-            .take(WINNING_SIZE) // Get the WINNING_SIZE winning number parts
-            .map(|n| n.parse::<usize>().expect(format!("Unable to parse the winning number: {}", n).as_str()))
-            .collect::<Vec<usize>>()
-            .try_into()
-            .expect("Incorrect number of winning numbers");
+        let (winning, scratch) = numbers.split_once('|')
+            .ok_or_else(|| format!("Card {}: missing '|' between winning and scratch numbers", id))?;
 
-        let mut scratch_numbers: [usize; SCRATCH_SIZE] = parts.rev()
-            .take(SCRATCH_SIZE) // Get the SCRATCH_SIZE scratch number parts from the end
-            .map(|n| n.parse::<usize>().expect(format!("Unable to parse the scratch number: {}", n).as_str()))
-            .collect::<Vec<usize>>()
-            .try_into()
-            .expect("Incorrect number of scratch numbers");
+        let parse_numbers = |numbers: &str, kind: &str| -> Result<Vec<usize>, String> {
+            let numbers = numbers.split_whitespace()
+                .map(|n| n.parse::<usize>().map_err(|_| format!("Card {}: unable to parse the {} number: {}", id, kind, n)))
+                .collect::<Result<Vec<usize>, String>>()?;
 
-        scratch_numbers.reverse(); // Reverse the scratch numbers to match the order in the string
+            if numbers.is_empty() {
+                return Err(format!("Card {}: no {} numbers", id, kind));
+            }
+            Ok(numbers)
+        };
+
+        let winning_numbers = parse_numbers(winning, "winning")?;
+        let scratch_numbers = parse_numbers(scratch, "scratch")?;
 
         Ok(Card { id, winning_numbers, scratch_numbers })
     }
@@ -124,41 +178,144 @@ impl Card {
         intersection.len()
     }
 
-    fn calculate_winnings(&self) -> usize {
+    // One point for the first match, doubled for each match after that
+    fn calculate_winnings(&self) -> Result<usize, CardError> {
         match self.number_of_winning_cards() {
-            0 => return 0,
-            1 => return 1,
-            matches => usize::pow(2, (matches-1).try_into().expect("Can't calculate winnings")),
+            0 => Ok(0),
+            matches => u32::try_from(matches - 1).ok()
+                .and_then(|exponent| 1usize.checked_shl(exponent))
+                .ok_or(CardError::Overflow(self.id)),
         }
     }
 }
 
+// A simulation needs at least one game to estimate anything from
+fn parse_trials(value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(0) => Err("Please provide at least one trial after --trials".to_string()),
+        Ok(trials) => Ok(trials),
+        Err(e) => Err(format!("Failed to parse trial count '{}': {}", value, e)),
+    }
+}
+
+// Compare exact expectations with a simulation for randomly generated games
+fn analyse(generator: CardGenerator, cards: usize, trials: usize, seed: u64) {
+    if let Err(e) = generator.validate() {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+
+    println!("Match distribution:");
+    for (matches, probability) in generator.match_distribution().iter().enumerate() {
+        println!("  {:>2} matches: {:.6}", matches, probability);
+    }
+
+    let exact_points = generator.expected_points() * cards as f64;
+    let exact_copies = generator.expected_copies(cards);
+    println!("Exact expected points for {} cards: {:.4}", cards, exact_points);
+    println!("Exact expected total cards: {:.4}", exact_copies);
+
+    match generator.simulate(cards, trials, &mut StdRng::seed_from_u64(seed)) {
+        Ok((points, copies)) => {
+            println!("Simulated points over {} games: {:.4} ± {:.4}", trials, points.mean, points.standard_error);
+            println!("Simulated total cards: {:.4} ± {:.4}", copies.mean, copies.standard_error);
+        },
+        Err(e) => println!("Simulation failed: {}", e),
+    }
+}
+
 fn main() {
-    // Get file name from command line
+    // Get file name (or "analyse") and options from command line
     let args: Vec<String> = env::args().collect();
-    let filename = args.get(1).expect("Please provide a filename");
+    let mut filename = None;
+    let mut cascade_format = None;
+    let mut streaming = false;
+    let mut generator = CardGenerator { winning: 10, scratch: 25, min: 1, max: 99 };
+    let mut cards = 20;
+    let mut trials = 1000;
+    let mut seed = 2023;
+
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--cascade" => cascade_format = Some(args.next().expect("Please provide table or dot after --cascade").clone()),
+            "--stream" => streaming = true,
+            "--winning" => generator.winning = args.next().expect("Please provide a count after --winning").parse().expect("Failed to parse winning count"),
+            "--scratch" => generator.scratch = args.next().expect("Please provide a count after --scratch").parse().expect("Failed to parse scratch count"),
+            "--min" => generator.min = args.next().expect("Please provide a number after --min").parse().expect("Failed to parse minimum"),
+            "--max" => generator.max = args.next().expect("Please provide a number after --max").parse().expect("Failed to parse maximum"),
+            "--cards" => cards = args.next().expect("Please provide a count after --cards").parse().expect("Failed to parse card count"),
+            "--trials" => trials = parse_trials(args.next().expect("Please provide a count after --trials")).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }),
+            "--seed" => seed = args.next().expect("Please provide a seed after --seed").parse().expect("Failed to parse seed"),
+            _ => filename = Some(arg),
+        }
+    }
+    if streaming && cascade_format.is_some() {
+        eprintln!("Error: --cascade needs every card kept, so it can't be combined with --stream");
+        std::process::exit(1);
+    }
+    if filename.map(|arg| arg.as_str()) == Some("analyse") {
+        analyse(generator, cards, trials, seed);
+        return;
+    }
+    let filename = filename.expect("Please provide a filename");
 
     let file = File::open(filename).expect("Failed to open file");
     let reader = BufReader::new(file);
 
+    if streaming {
+        let mut stream = StreamingCardStack::new();
+
+        for line in reader.lines() {
+            let line = line.expect("Failed to read line");
+            let card = Card::try_from(line).expect("Unable to parse card");
+            if let Err(e) = stream.add_card(&card) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+
+        println!("Result is: {:?}", stream.total_winnings());
+
+        println!("Total number of cards: {}", stream.count_copies());
+        return;
+    }
+
     let mut stack = CardStack::new();
 
     for line in reader.lines() {
         let line = line.expect("Failed to read line");
         let card = Card::try_from(line).expect("Unable to parse card");
-        stack.add_card(card);
+        if let Err(e) = stack.add_card(card) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
     }
 
-    println!("Total number of cards: {}", stack.count_copies());
+    let (winnings, copies) = match (stack.total_winnings(), stack.count_copies()) {
+        (Ok(winnings), Ok(copies)) => (winnings, copies),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        },
+    };
 
-    // let total_winnings = cards.iter()
-    //     .map(|card| card.calculate_winnings())
-    //     .sum::<usize>();
+    println!("Result is: {:?}", winnings);
 
-    // println!("Result is: {:?}", total_winnings);
+    println!("Total number of cards: {}", copies);
 
+    match cascade_format.as_deref() {
+        Some("table") => print!("{}", cascade::render_table(&stack.cascade())),
+        Some("dot") => print!("{}", cascade::render_dot(&stack.cascade())),
+        Some(format) => panic!("Unknown cascade format {}, expected table or dot", format),
+        None => {},
+    }
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
 
@@ -168,8 +325,8 @@ mod tests {
         let card = Card::try_from(line).expect("Unable to parse card");
         assert_eq!(card, Card {
             id: 1,
-            winning_numbers: [1, 2, 3, 4, 5, 6, 7, 8, 9, 10],
-            scratch_numbers: [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25],
+            winning_numbers: vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10],
+            scratch_numbers: vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25],
         });
     }
 
@@ -177,37 +334,37 @@ mod tests {
     fn test_card_calculate_winnings() {
         let card = Card {
             id: 1,
-            winning_numbers: [1, 2, 3, 4, 5, 6, 7, 8, 9, 10],
-            scratch_numbers: [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25],
+            winning_numbers: vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10],
+            scratch_numbers: vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25],
         };
-        assert_eq!(card.calculate_winnings(), 512);
+        assert_eq!(card.calculate_winnings(), Ok(512));
     }
 
     #[test]
     fn test_card_calculate_winnings_scoring() {
         let card = Card {id: 1,
-            winning_numbers: [91, 92, 93, 94, 95, 96, 97, 98, 99, 26],
-            scratch_numbers: [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25],
+            winning_numbers: vec![91, 92, 93, 94, 95, 96, 97, 98, 99, 26],
+            scratch_numbers: vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25],
         };
-        assert_eq!(card.calculate_winnings(), 0);
+        assert_eq!(card.calculate_winnings(), Ok(0));
 
         let card = Card {id: 1,
-            winning_numbers: [1, 92, 93, 94, 95, 96, 97, 98, 99, 26],
-            scratch_numbers: [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25],
+            winning_numbers: vec![1, 92, 93, 94, 95, 96, 97, 98, 99, 26],
+            scratch_numbers: vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25],
         };
-        assert_eq!(card.calculate_winnings(), 1);
+        assert_eq!(card.calculate_winnings(), Ok(1));
 
         let card = Card {id: 1,
-            winning_numbers: [1, 2, 93, 94, 95, 96, 97, 98, 99, 26],
-            scratch_numbers: [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25],
+            winning_numbers: vec![1, 2, 93, 94, 95, 96, 97, 98, 99, 26],
+            scratch_numbers: vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25],
         };
-        assert_eq!(card.calculate_winnings(), 2);
+        assert_eq!(card.calculate_winnings(), Ok(2));
 
         let card = Card {id: 1,
-            winning_numbers: [1, 2, 3, 94, 95, 96, 97, 98, 99, 26],
-            scratch_numbers: [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25],
+            winning_numbers: vec![1, 2, 3, 94, 95, 96, 97, 98, 99, 26],
+            scratch_numbers: vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25],
         };
-        assert_eq!(card.calculate_winnings(), 4);
+        assert_eq!(card.calculate_winnings(), Ok(4));
     }
 
 
@@ -217,9 +374,9 @@ mod tests {
 
         stack.add_card(Card {
             id: 1,
-            winning_numbers: [1, 2, 3, 94, 95, 96, 97, 98, 99, 26],
-            scratch_numbers: [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25],
-        });
+            winning_numbers: vec![1, 2, 3, 94, 95, 96, 97, 98, 99, 26],
+            scratch_numbers: vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25],
+        }).expect("Unable to add card");
         assert_eq!(stack.cards.len(), 4);
         assert_eq!(stack.cards.get(&1).unwrap().copies, 1);
         assert_eq!(stack.cards.get(&2).unwrap().copies, 1);
@@ -228,9 +385,9 @@ mod tests {
 
         stack.add_card(Card {
             id: 2,
-            winning_numbers: [1, 2, 93, 94, 95, 96, 97, 98, 99, 26],
-            scratch_numbers: [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25],
-        });
+            winning_numbers: vec![1, 2, 93, 94, 95, 96, 97, 98, 99, 26],
+            scratch_numbers: vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25],
+        }).expect("Unable to add card");
         assert_eq!(stack.cards.len(), 4);
         assert_eq!(stack.cards.get(&1).unwrap().copies, 1);
         assert_eq!(stack.cards.get(&2).unwrap().copies, 2);
@@ -239,9 +396,9 @@ mod tests {
 
         stack.add_card(Card {
             id: 3,
-            winning_numbers: [1, 2, 93, 94, 95, 96, 97, 98, 99, 26],
-            scratch_numbers: [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25],
-        });
+            winning_numbers: vec![1, 2, 93, 94, 95, 96, 97, 98, 99, 26],
+            scratch_numbers: vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25],
+        }).expect("Unable to add card");
         assert_eq!(stack.cards.len(), 5);
         assert_eq!(stack.cards.get(&1).unwrap().copies, 1);
         assert_eq!(stack.cards.get(&2).unwrap().copies, 2);
@@ -249,4 +406,86 @@ mod tests {
         assert_eq!(stack.cards.get(&4).unwrap().copies, 7);
         assert_eq!(stack.cards.get(&5).unwrap().copies, 4);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_card_from_line_variable_sizes() {
+        let line = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53".to_string();
+        let card = Card::try_from(line).expect("Unable to parse card");
+        assert_eq!(card, Card {
+            id: 1,
+            winning_numbers: vec![41, 48, 83, 86, 17],
+            scratch_numbers: vec![83, 86, 6, 31, 17, 9, 48, 53],
+        });
+        assert_eq!(card.calculate_winnings(), Ok(8));
+
+        let line = "Card  12: 7 | 7 8".to_string();
+        let card = Card::try_from(line).expect("Unable to parse card");
+        assert_eq!(card.winning_numbers, vec![7]);
+        assert_eq!(card.scratch_numbers, vec![7, 8]);
+    }
+
+    #[test]
+    fn test_card_from_line_errors() {
+        assert_eq!(Card::try_from("Card 3: 1 2 3 4 5".to_string()), Err("Card 3: missing '|' between winning and scratch numbers".to_string()));
+        assert_eq!(Card::try_from("Card 4: 1 x | 3".to_string()), Err("Card 4: unable to parse the winning number: x".to_string()));
+        assert_eq!(Card::try_from("Card 5: 1 2 | ".to_string()), Err("Card 5: no scratch numbers".to_string()));
+        assert_eq!(Card::try_from("Card x: 1 | 2".to_string()), Err("Unable to parse card id: x".to_string()));
+    }
+
+    #[test]
+    fn test_card_stack_both_scores() {
+        let mut stack = CardStack::new();
+        let lines = [
+            "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53",
+            "Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19",
+            "Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1",
+            "Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83",
+            "Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36",
+            "Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11",
+        ];
+        for line in lines {
+            stack.add_card(Card::try_from(line.to_string()).expect("Unable to parse card")).expect("Unable to add card");
+        }
+
+        assert_eq!(stack.total_winnings(), Ok(13));
+        assert_eq!(stack.count_copies(), Ok(30));
+    }
+
+    #[test]
+    fn test_card_stack_cascade() {
+        let mut stack = CardStack::new();
+        stack.add_card(Card::try_from("Card 1: 1 2 | 1 2".to_string()).expect("Unable to parse card")).expect("Unable to add card");
+        stack.add_card(Card::try_from("Card 2: 1 | 1".to_string()).expect("Unable to parse card")).expect("Unable to add card");
+        stack.add_card(Card::try_from("Card 3: 1 | 2".to_string()).expect("Unable to parse card")).expect("Unable to add card");
+
+        assert_eq!(stack.cascade(), vec![
+            CascadeEntry { id: 1, matches: 2, copies: 1, sources: vec![] },
+            CascadeEntry { id: 2, matches: 1, copies: 2, sources: vec![(1, 1)] },
+            CascadeEntry { id: 3, matches: 0, copies: 4, sources: vec![(1, 1), (2, 2)] },
+        ]);
+    }
+
+    #[test]
+    fn test_card_stack_errors() {
+        let mut stack = CardStack::new();
+        stack.add_card(Card::try_from("Card 1: 1 | 1".to_string()).expect("Unable to parse card")).expect("Unable to add card");
+        assert_eq!(stack.add_card(Card::try_from("Card 1: 2 | 2".to_string()).expect("Unable to parse card")), Err(CardError::Duplicate(1)));
+        assert_eq!(stack.count_copies(), Ok(1));
+
+        // 65 matches is worth 2^64 points, one more than a usize holds
+        let numbers = (1..=65).map(|n| n.to_string()).collect::<Vec<_>>().join(" ");
+        let card = Card::try_from(format!("Card 2: {} | {}", numbers, numbers)).expect("Unable to parse card");
+        assert_eq!(card.calculate_winnings(), Err(CardError::Overflow(2)));
+
+        let numbers = (1..=64).map(|n| n.to_string()).collect::<Vec<_>>().join(" ");
+        let card = Card::try_from(format!("Card 3: {} | {}", numbers, numbers)).expect("Unable to parse card");
+        assert_eq!(card.calculate_winnings(), Ok(1 << 63));
+    }
+
+    #[test]
+    fn test_parse_trials() {
+        assert_eq!(parse_trials("1000"), Ok(1000));
+        assert_eq!(parse_trials("0"), Err("Please provide at least one trial after --trials".to_string()));
+        assert!(parse_trials("-1").is_err());
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::Card;
+
+// Computes both scores for a stream of cards in id order without keeping the
+// cards. A card can only win copies of the next `matches` cards, so only the
+// copies already won for upcoming cards are held. Copy counts can double with
+// every card, so totals are u128 and every step is checked.
+pub struct StreamingCardStack {
+    // Copies already won for the cards after the last one read, nearest first
+    pending: VecDeque<u128>,
+    last_id: Option<usize>,
+    total_copies: u128,
+    total_winnings: u128,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum StreamError {
+    Duplicate(usize),
+    OutOfOrder { id: usize, previous: usize },
+    Overflow(usize),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StreamError::Duplicate(id) => write!(f, "Card {} appears more than once", id),
+            StreamError::OutOfOrder { id, previous } => write!(f, "Card {} comes after card {}", id, previous),
+            StreamError::Overflow(id) => write!(f, "Card {}: copy count or winnings overflowed", id),
+        }
+    }
+}
+
+impl StreamingCardStack {
+    pub fn new() -> Self {
+        StreamingCardStack {
+            pending: VecDeque::new(),
+            last_id: None,
+            total_copies: 0,
+            total_winnings: 0,
+        }
+    }
+
+    pub fn add_card(&mut self, card: &Card) -> Result<(), StreamError> {
+        let id = card.id;
+
+        if let Some(previous) = self.last_id {
+            if id == previous {
+                return Err(StreamError::Duplicate(id));
+            }
+            if id < previous {
+                return Err(StreamError::OutOfOrder { id, previous });
+            }
+            // Copies won for cards missing from the stream are lost
+            let gap = id - previous - 1;
+            self.pending.drain(..gap.min(self.pending.len()));
+        }
+        self.last_id = Some(id);
+
+        let copies = self.pending.pop_front().unwrap_or(0)
+            .checked_add(1)
+            .ok_or(StreamError::Overflow(id))?;
+        self.total_copies = self.total_copies.checked_add(copies).ok_or(StreamError::Overflow(id))?;
+
+        let matches = card.number_of_winning_cards();
+        let winnings = match matches {
+            0 => 0,
+            matches => u32::try_from(matches - 1).ok()
+                .and_then(|exponent| 2u128.checked_pow(exponent))
+                .ok_or(StreamError::Overflow(id))?,
+        };
+        self.total_winnings = self.total_winnings.checked_add(winnings).ok_or(StreamError::Overflow(id))?;
+
+        if self.pending.len() < matches {
+            self.pending.resize(matches, 0);
+        }
+        for pending in self.pending.iter_mut().take(matches) {
+            *pending = pending.checked_add(copies).ok_or(StreamError::Overflow(id))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn count_copies(&self) -> u128 {
+        self.total_copies
+    }
+
+    pub fn total_winnings(&self) -> u128 {
+        self.total_winnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(id: usize, matches: usize) -> Card {
+        Card {
+            id,
+            winning_numbers: (1..=matches.max(1)).collect(),
+            scratch_numbers: if matches == 0 { vec![0] } else { (1..=matches).collect() },
+        }
+    }
+
+    #[test]
+    fn test_streaming_matches_card_stack() {
+        let mut stream = StreamingCardStack::new();
+        for (id, matches) in [(1, 4), (2, 2), (3, 2), (4, 1), (5, 0), (6, 0)] {
+            stream.add_card(&card(id, matches)).expect("Unable to add card");
+        }
+
+        assert_eq!(stream.count_copies(), 30);
+        assert_eq!(stream.total_winnings(), 13);
+        assert!(stream.pending.is_empty());
+    }
+
+    #[test]
+    fn test_streaming_order_errors() {
+        let mut stream = StreamingCardStack::new();
+        stream.add_card(&card(2, 1)).expect("Unable to add card");
+
+        assert_eq!(stream.add_card(&card(2, 1)), Err(StreamError::Duplicate(2)));
+        assert_eq!(stream.add_card(&card(1, 1)), Err(StreamError::OutOfOrder { id: 1, previous: 2 }));
+
+        // Skipping card 3 drops the copy it won, so card 4 is alone
+        stream.add_card(&card(4, 0)).expect("Unable to add card");
+        assert_eq!(stream.count_copies(), 2);
+    }
+
+    #[test]
+    fn test_streaming_sparse_ids() {
+        // A huge gap between ids is skipped in one step rather than one id at a time
+        let mut stream = StreamingCardStack::new();
+        stream.add_card(&card(1, 3)).expect("Unable to add card");
+        stream.add_card(&card(3, 1)).expect("Unable to add card");
+        stream.add_card(&card(1_000_000_000_000, 2)).expect("Unable to add card");
+        stream.add_card(&card(1_000_000_000_001, 0)).expect("Unable to add card");
+
+        // Card 3 has the copy card 1 won, the rest were won for missing cards
+        assert_eq!(stream.count_copies(), 1 + 2 + 1 + 2);
+        assert_eq!(stream.pending.len(), 1);
+    }
+
+    #[test]
+    fn test_streaming_large_counts() {
+        // Every card wins a copy of each card after it, so copies double each card
+        let mut stream = StreamingCardStack::new();
+        for id in 1..=100 {
+            stream.add_card(&card(id, 101 - id)).expect("Unable to add card");
+        }
+        assert_eq!(stream.count_copies(), (1u128 << 100) - 1);
+        assert_eq!(stream.pending.len(), 1);
+
+        let mut stream = StreamingCardStack::new();
+        let result = (1..=200).try_for_each(|id| stream.add_card(&card(id, 201 - id)));
+        assert!(matches!(result, Err(StreamError::Overflow(_))));
+    }
+}
@@ -0,0 +1,91 @@
+use std::time::Instant;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::{Almanac, SeedMode};
+
+const CATEGORIES: [&str; 8] = ["seed", "soil", "fertilizer", "water", "light", "temperature", "humidity", "location"];
+
+// Almanac text with `rules` rules in every map. Each map cuts 0..rules*1000
+// into blocks of random length and sends them to the same blocks shuffled, so
+// rules never overlap and every value has exactly one image.
+pub fn generate_almanac(rules: usize, seed_ranges: usize, seed: u64) -> String {
+    assert!(rules > 0, "Generated almanacs need at least one rule per map");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let span = rules as u64 * 1000;
+
+    let mut almanac = String::from("seeds:");
+    for _ in 0..seed_ranges {
+        almanac += &format!(" {} {}", rng.gen_range(0..span), rng.gen_range(1..=span / 10));
+    }
+    almanac.push('\n');
+
+    for pair in CATEGORIES.windows(2) {
+        let mut cuts: Vec<u64> = (1..rules).map(|_| rng.gen_range(1..span)).collect();
+        cuts.push(0);
+        cuts.push(span);
+        cuts.sort();
+        cuts.dedup();
+
+        let blocks: Vec<(u64, u64)> = cuts.windows(2).map(|cut| (cut[0], cut[1] - cut[0])).collect();
+        let mut order: Vec<usize> = (0..blocks.len()).collect();
+        order.shuffle(&mut rng);
+
+        almanac += &format!("\n{}-to-{} map:\n", pair[0], pair[1]);
+        let mut destination = 0;
+        for &block in &order {
+            let (source, length) = blocks[block];
+            almanac += &format!("{} {} {}\n", destination, source, length);
+            destination += length;
+        }
+    }
+
+    almanac
+}
+
+// Time parsing and traversing a generated almanac
+pub fn run(rules: usize, seed_ranges: usize, seed: u64) {
+    let input = generate_almanac(rules, seed_ranges, seed);
+    println!("Generated {} maps of {} rules and {} seed ranges ({} bytes)", CATEGORIES.len() - 1, rules, seed_ranges, input.len());
+
+    let start = Instant::now();
+    let almanac = Almanac::from_reader(input.as_bytes(), SeedMode::Ranges).expect("Failed to parse generated almanac");
+    println!("Parsed and indexed in {:?}", start.elapsed());
+
+    let start = Instant::now();
+    let mut ranges = almanac.seeds.clone();
+    for pair in CATEGORIES.windows(2) {
+        ranges = almanac.map_ranges(pair[0], pair[1], ranges).expect("Generated almanac should chain");
+        println!("  {:>12}: {} ranges", pair[1], ranges.len());
+    }
+    let lowest = ranges.iter().map(|range| range.start).min().expect("No seeds in almanac");
+    println!("Traversed in {:?}, minimum location {}", start.elapsed(), lowest);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_almanac_matches_composition() {
+        let input = generate_almanac(200, 5, 7);
+        let almanac = Almanac::from_reader(input.as_bytes(), SeedMode::Ranges).unwrap();
+        assert!(almanac.maps.values().all(|map| map.rules.len() <= 200));
+
+        // Every map is a permutation, so the seed ranges keep their total size
+        let locations = almanac.map_ranges("seed", "location", almanac.seeds.clone()).unwrap();
+        let size = |ranges: &[crate::Range]| ranges.iter().map(|range| range.end - range.start + 1).sum::<i64>();
+        assert_eq!(size(&locations), size(&crate::merge_ranges(almanac.seeds.clone())));
+
+        let composed = almanac.compose("seed", "location").unwrap();
+        let lowest = almanac.seeds.iter()
+            .flat_map(|range| [range.start, range.end, (range.start + range.end) / 2])
+            .filter_map(|seed| composed.apply(seed))
+            .min()
+            .unwrap();
+        assert!(locations[0].start <= lowest);
+        assert_eq!(composed.preimage(&locations), Ok(crate::merge_ranges(almanac.seeds.clone())));
+    }
+}
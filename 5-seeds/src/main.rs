@@ -1,6 +1,14 @@
+mod bench;
+mod piecewise;
+mod validate;
+
 use std::cmp::Ordering;
-use std::io::{BufRead, BufReader};
-use std::{env, fs::File};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::BufRead;
+use std::{env, fs};
+
+use piecewise::PiecewiseMap;
 
 #[derive(Eq, PartialEq, Debug, Clone)]
 struct Range {
@@ -20,6 +28,20 @@ impl PartialOrd for Range {
     }
 }
 
+// Sort ranges and join any that overlap or touch
+fn merge_ranges(mut ranges: Vec<Range>) -> Vec<Range> {
+    ranges.sort();
+
+    let mut merged: Vec<Range> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if last.end.saturating_add(1) >= range.start => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
 #[derive(PartialEq, Eq, Debug)]
 struct AlmanacMap<S, D> {
     source_start: S,
@@ -27,547 +49,334 @@ struct AlmanacMap<S, D> {
     range_length: i64,
 }
 
-enum AlmanacMapType {
-    SeedSoil,
-    SoilFertilizer,
-    FertilizerWater,
-    WaterLight,
-    LightTemperature,
-    TemperatureHumidity,
-    HumidityLocation,
+// The rules converting one category into the next, from an "X-to-Y map:" section
+#[derive(PartialEq, Eq, Debug)]
+struct CategoryMap {
+    source: String,
+    destination: String,
+    rules: Vec<AlmanacMap<i64, i64>>,
+    // The rules as sorted, non-overlapping pieces for binary searching
+    index: PiecewiseMap,
+}
+
+// How to read the numbers on the "seeds:" line
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum SeedMode {
+    // Every number is a seed
+    Individual,
+    // Numbers are pairs of range start and length
+    Ranges,
 }
 
 struct Almanac {
     seeds: Vec<Range>,
-    seed_soil: Vec<AlmanacMap<i64, i64>>,
-    soil_fertilizer: Vec<AlmanacMap<i64, i64>>,
-    fertilizer_water: Vec<AlmanacMap<i64, i64>>,
-    water_light: Vec<AlmanacMap<i64, i64>>,
-    light_temperature: Vec<AlmanacMap<i64, i64>>,
-    temperature_humidity: Vec<AlmanacMap<i64, i64>>,
-    humidity_location: Vec<AlmanacMap<i64, i64>>,
+    // Numbers from the "seeds:" line, for reading them in another mode
+    seed_values: Vec<i64>,
+    // Maps keyed by their source category; each category maps to at most one other
+    maps: HashMap<String, CategoryMap>,
 }
 
-// impl AlmanacMap<i64, i64> {
-//     fn get_dest(&self, source: Range) -> (Option<Vec<Range>>, Option<Vec<Range>>) {
-//         // Check if source is out of range
-//         if source.end < self.source_start || source.start > self.source_start + self.range_length - 1 {
-//             return (None, Some(vec![source]));
-//         }
-
-//         let mut source = source;
-
-//         let mut new_ranges = Vec::new();
-//         let mut remaining_ranges = Vec::new();
-
-//         let offset = self.destination_start as i64 - self.source_start as i64;
-
-//         // Create new destination range(s)
-//         if source.start < self.source_start {
-//             // Source range starts before this map
-//             remaining_ranges.push(Range {
-//                 start: source.start,
-//                 end: self.source_start - 1,
-//             });
-//             source.start = self.source_start;
-
-//             if source.end < self.source_start + self.range_length {
-//                 new_ranges.push(Range {
-//                     start: (source.start as i64 + offset) as i64,
-//                     end: (source.end as i64 + offset) as i64,
-//                 });
-//             } else {
-//                 new_ranges.push(Range {
-//                     start: (source.start as i64 + offset) as i64,
-//                     end: (self.source_start as i64 + self.range_length as i64 - 1 as i64 + offset) as i64,
-//                 });
-//             }
-//         } else if source.end < self.source_start + self.range_length {
-//             // Source entirely within this map
-//             new_ranges.push(Range {
-//                 start: (source.start as i64 + offset) as i64,
-//                 end: (source.end as i64 + offset) as i64,
-//             });
-
-//             // No remainder, so different return
-//             return (Some(new_ranges), None)
-//         } else {
-//             // Source range ends after this map
-//             new_ranges.push(Range {
-//                 start: (source.start as i64 + offset) as i64,
-//                 end: (self.source_start as i64 + self.range_length as i64 - 1 as i64 + offset) as i64,
-//             });
-//             remaining_ranges.push(Range {
-//                 start: self.source_start + self.range_length,
-//                 end: source.end,
-//             });
-//         }
-//         (Some(new_ranges), Some(remaining_ranges))
-//     }
-// }
+#[derive(PartialEq, Eq, Debug)]
+enum AlmanacError {
+    UnknownCategory(String),
+    // The chain from the first category ends at the second without reaching the target
+    Unreachable { from: String, to: String, end: String },
+    // Following the chain came back to a category already visited
+    Cycle(Vec<String>),
+    // Combining the maps moves some value outside i64
+    Overflow(String),
+}
 
-impl Almanac {
-    fn traverse_almanac_map(
-        &self,
-        sources: Vec<Range>,
-        almanac_map_type: AlmanacMapType,
-    ) -> Vec<Range> {
-        let almanac_map = match almanac_map_type {
-            AlmanacMapType::SeedSoil => &self.seed_soil,
-            AlmanacMapType::SoilFertilizer => &self.soil_fertilizer,
-            AlmanacMapType::FertilizerWater => &self.fertilizer_water,
-            AlmanacMapType::WaterLight => &self.water_light,
-            AlmanacMapType::LightTemperature => &self.light_temperature,
-            AlmanacMapType::TemperatureHumidity => &self.temperature_humidity,
-            AlmanacMapType::HumidityLocation => &self.humidity_location,
-        };
+impl fmt::Display for AlmanacError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AlmanacError::UnknownCategory(category) => write!(f, "No map mentions category '{}'", category),
+            AlmanacError::Unreachable { from, to, end } => write!(
+                f, "Cannot reach '{}' from '{}': the chain stops at '{}'", to, from, end
+            ),
+            AlmanacError::Cycle(categories) => write!(f, "Maps form a cycle: {}", categories.join(" -> ")),
+            AlmanacError::Overflow(message) => write!(f, "{}", message),
+        }
+    }
+}
 
+impl Almanac {
+    // Apply one map to every source range. Each range is split by binary
+    // searching the map's pieces for the first one it touches, and the results
+    // are merged so fragments don't pile up from stage to stage. Piece images
+    // are checked to fit in an i64 when the index is built.
+    fn traverse_almanac_map(sources: Vec<Range>, index: &PiecewiseMap) -> Vec<Range> {
         let mut new_ranges: Vec<Range> = Vec::new();
 
-        // Ran out of time, got working with the wonderful walkthrough here: https://nickymeuleman.netlify.app/garden/aoc2023-day05#part-2
-        for range in &sources {
-            let mut curr = range.clone();
-
-            for rule in almanac_map {
-                let offset = rule.destination_start as i64 - rule.source_start as i64;
-                let rule_applies = curr.start <= curr.end
-                    && curr.start <= rule.source_start + rule.range_length
-                    && curr.end >= rule.source_start;
-
-                if rule_applies {
-                    if curr.start < rule.source_start {
-                        new_ranges.push(Range {
-                            start: curr.start,
-                            end: rule.source_start - 1,
-                        });
-                        curr.start = rule.source_start;
-                        if curr.end < rule.source_start + rule.range_length {
-                            new_ranges.push(Range {
-                                start: (curr.start as i64 + offset) as i64,
-                                end: (curr.end as i64 + offset) as i64,
-                            });
-                            curr.start = curr.end + 1;
-                        } else {
-                            new_ranges.push(Range {
-                                start: (curr.start as i64 + offset) as i64,
-                                end: (rule.source_start as i64 + rule.range_length as i64
-                                    - 1 as i64
-                                    + offset) as i64,
-                            });
-                            curr.start = rule.source_start + rule.range_length;
-                        }
-                    } else if curr.end < rule.source_start + rule.range_length {
-                        new_ranges.push(Range {
-                            start: (curr.start as i64 + offset) as i64,
-                            end: (curr.end as i64 + offset) as i64,
-                        });
-                        curr.start = curr.end + 1;
-                    } else {
-                        new_ranges.push(Range {
-                            start: (curr.start as i64 + offset) as i64,
-                            end: (rule.source_start as i64 + rule.range_length as i64 - 1 as i64
-                                + offset) as i64,
-                        });
-                        curr.start = rule.source_start + rule.range_length;
-                    }
-                }
-            }
-            if curr.start <= curr.end {
-                new_ranges.push(curr);
+        for range in merge_ranges(sources) {
+            let first = index.pieces.partition_point(|piece| piece.end < range.start);
+
+            for piece in index.pieces[first..].iter().take_while(|piece| piece.start <= range.end) {
+                new_ranges.push(Range {
+                    start: range.start.max(piece.start) + piece.offset,
+                    end: range.end.min(piece.end) + piece.offset,
+                });
             }
         }
-        new_ranges
-    }
-
-    fn from_reader<R: BufRead>(reader: R) -> Result<Self, String> {
-        let mut seeds: Vec<Range> = Vec::new();
-        let mut seed_soil: Vec<AlmanacMap<i64, i64>> = Vec::new();
-        let mut soil_fertilizer: Vec<AlmanacMap<i64, i64>> = Vec::new();
-        let mut fertilizer_water: Vec<AlmanacMap<i64, i64>> = Vec::new();
-        let mut water_light: Vec<AlmanacMap<i64, i64>> = Vec::new();
-        let mut light_temperature: Vec<AlmanacMap<i64, i64>> = Vec::new();
-        let mut temperature_humidity: Vec<AlmanacMap<i64, i64>> = Vec::new();
-        let mut humidity_location: Vec<AlmanacMap<i64, i64>> = Vec::new();
-
-        let mut lines = reader.lines();
 
-        // Get seeds
-        while let Some(line) = lines.next() {
-            let line = line.expect("Failed to read seeds line");
+        merge_ranges(new_ranges)
+    }
 
-            if line.is_empty() {
-                continue;
+    // The maps to follow, in order, to convert `from` into `to`
+    fn chain(&self, from: &str, to: &str) -> Result<Vec<&CategoryMap>, AlmanacError> {
+        for category in [from, to] {
+            let known = self.maps.contains_key(category)
+                || self.maps.values().any(|map| map.destination == category);
+            if !known {
+                return Err(AlmanacError::UnknownCategory(category.to_string()));
             }
+        }
 
-            let seeds_string = line.trim_start_matches("seeds:");
-            for seed_pair in seeds_string
-                .split_whitespace()
-                .map(|seed| {
-                    seed.parse()
-                        .map_err(|e| format!("Failed to parse seed: {}", e))
-                })
-                .collect::<Result<Vec<_>, _>>()?
-                .chunks(2)
-            {
-                let start = seed_pair[0];
-                let end = seed_pair[0] + seed_pair[1] - 1;
-                seeds.push(Range { start, end });
+        let mut chain = Vec::new();
+        let mut visited = vec![from.to_string()];
+        let mut current = from;
+
+        while current != to {
+            let Some(map) = self.maps.get(current) else {
+                return Err(AlmanacError::Unreachable { from: from.to_string(), to: to.to_string(), end: current.to_string() });
+            };
+            visited.push(map.destination.clone());
+            if visited[..visited.len() - 1].contains(&map.destination) {
+                return Err(AlmanacError::Cycle(visited));
             }
-
-            break;
+            chain.push(map);
+            current = &map.destination;
         }
 
-        // Get seed_soil
-        let mut found = false;
-        while let Some(line) = lines.next() {
-            let line = line.expect("Failed to read seed_soil line");
-
-            if line.is_empty() {
-                if !found {
-                    continue; // Whitespace before
-                } else {
-                    break; // Whitespace after
-                }
-            }
+        Ok(chain)
+    }
 
-            if line.starts_with("seed-to-soil map:") {
-                found = true;
-                continue;
-            }
+    // Convert ranges of `from` values into the `to` ranges they end up as
+    fn map_ranges(&self, from: &str, to: &str, ranges: Vec<Range>) -> Result<Vec<Range>, AlmanacError> {
+        Ok(self.chain(from, to)?.iter()
+            .fold(ranges, |ranges, map| Almanac::traverse_almanac_map(ranges, &map.index)))
+    }
 
-            //Create AlmanacMap from a line like "50 98 2"
-            let mut map_data = line.split_whitespace();
-            seed_soil.push(AlmanacMap {
-                destination_start: map_data
-                    .next()
-                    .expect("Failed to get soil")
-                    .trim()
-                    .parse()
-                    .expect("Failed to parse soil"),
-                source_start: map_data
-                    .next()
-                    .expect("Failed to get seed")
-                    .trim()
-                    .parse()
-                    .expect("Failed to parse seed"),
-                range_length: map_data
-                    .next()
-                    .expect("Failed to get range length")
-                    .trim()
-                    .parse()
-                    .expect("Failed to parse range length"),
-            });
-        }
+    // The whole chain from `from` to `to` as a single function
+    fn compose(&self, from: &str, to: &str) -> Result<PiecewiseMap, AlmanacError> {
+        self.chain(from, to)?.iter()
+            .try_fold(PiecewiseMap::identity(), |composed, map| composed.then(&map.index))
+            .map_err(AlmanacError::Overflow)
+    }
 
-        // Get soil_fertilizer
-        let mut found = false;
-        while let Some(line) = lines.next() {
-            let line = line.expect("Failed to read soil_fertilizer line");
+    // The `from` ranges that end up somewhere in the `to` ranges
+    fn reverse_ranges(&self, from: &str, to: &str, ranges: &[Range]) -> Result<Vec<Range>, AlmanacError> {
+        self.compose(from, to)?.preimage(ranges).map_err(AlmanacError::Overflow)
+    }
 
-            if line.is_empty() {
-                if !found {
-                    continue; // Whitespace before
-                } else {
-                    break; // Whitespace after
-                }
-            }
+    // The seeds as read in the given mode, which may differ from the one parsed with
+    fn seeds_as(&self, seed_mode: SeedMode) -> Result<Vec<Range>, String> {
+        seed_ranges(&self.seed_values, seed_mode)
+    }
 
-            if line.starts_with("soil-to-fertilizer map:") {
-                found = true;
-                continue;
-            }
+    fn from_reader<R: BufRead>(reader: R, seed_mode: SeedMode) -> Result<Self, String> {
+        let mut almanac = Almanac::parse(reader)?;
 
-            //Create AlmanacMap from a line like "50 98 2"
-            let mut map_data = line.split_whitespace();
-            soil_fertilizer.push(AlmanacMap {
-                destination_start: map_data
-                    .next()
-                    .expect("Failed to get soil")
-                    .trim()
-                    .parse()
-                    .expect("Failed to parse soil"),
-                source_start: map_data
-                    .next()
-                    .expect("Failed to get seed")
-                    .trim()
-                    .parse()
-                    .expect("Failed to parse seed"),
-                range_length: map_data
-                    .next()
-                    .expect("Failed to get range length")
-                    .trim()
-                    .parse()
-                    .expect("Failed to parse range length"),
-            });
+        for map in almanac.maps.values_mut() {
+            map.index = PiecewiseMap::from_rules(&map.rules)
+                .map_err(|e| format!("{}-to-{} map: {}", map.source, map.destination, e))?;
         }
 
-        // Get fertilizer_water
-        let mut found = false;
-        while let Some(line) = lines.next() {
-            let line = line.expect("Failed to read fertilizer_water line");
+        almanac.seeds = seed_ranges(&almanac.seed_values, seed_mode)?;
+        Ok(almanac)
+    }
+
+    // Read the seed numbers and every map's rules, sorted by source, without
+    // building the maps' indexes or reading the seeds in any mode. Lint uses
+    // this so it can report rules that no index could hold.
+    fn parse<R: BufRead>(reader: R) -> Result<Self, String> {
+        let mut seed_values: Vec<i64> = Vec::new();
+        let mut maps: HashMap<String, CategoryMap> = HashMap::new();
+        // Source category of the section being read
+        let mut current: Option<String> = None;
 
-            if line.is_empty() {
-                if !found {
-                    continue; // Whitespace before
-                } else {
-                    break; // Whitespace after
-                }
-            }
+        for (number, line) in reader.lines().enumerate() {
+            let line = line.map_err(|e| format!("Failed to read line {}: {}", number + 1, e))?;
+            let line = line.trim();
 
-            if line.starts_with("fertilizer-to-water map:") {
-                found = true;
+            if line.is_empty() {
                 continue;
             }
 
-            //Create AlmanacMap from a line like "50 98 2"
-            let mut map_data = line.split_whitespace();
-            fertilizer_water.push(AlmanacMap {
-                destination_start: map_data
-                    .next()
-                    .expect("Failed to get soil")
-                    .trim()
-                    .parse()
-                    .expect("Failed to parse soil"),
-                source_start: map_data
-                    .next()
-                    .expect("Failed to get seed")
-                    .trim()
-                    .parse()
-                    .expect("Failed to parse seed"),
-                range_length: map_data
-                    .next()
-                    .expect("Failed to get range length")
-                    .trim()
-                    .parse()
-                    .expect("Failed to parse range length"),
-            });
-        }
+            if let Some(seeds_string) = line.strip_prefix("seeds:") {
+                let values = seeds_string
+                    .split_whitespace()
+                    .map(|seed| {
+                        seed.parse::<i64>()
+                            .map_err(|e| format!("Failed to parse seed: {}", e))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
 
-        // Get water_light
-        let mut found = false;
-        while let Some(line) = lines.next() {
-            let line = line.expect("Failed to read water_light line");
-
-            if line.is_empty() {
-                if !found {
-                    continue; // Whitespace before
-                } else {
-                    break; // Whitespace after
-                }
+                seed_values.extend(values);
+                continue;
             }
 
-            if line.starts_with("water-to-light map:") {
-                found = true;
+            if let Some(name) = line.strip_suffix(" map:") {
+                let (source, destination) = name.split_once("-to-")
+                    .ok_or_else(|| format!("Line {}: map header should look like 'X-to-Y map:'", number + 1))?;
+                if maps.contains_key(source) {
+                    return Err(format!("Line {}: a map from '{}' already exists", number + 1, source));
+                }
+                maps.insert(source.to_string(), CategoryMap {
+                    source: source.to_string(),
+                    destination: destination.to_string(),
+                    rules: Vec::new(),
+                    index: PiecewiseMap::identity(),
+                });
+                current = Some(source.to_string());
                 continue;
             }
 
             //Create AlmanacMap from a line like "50 98 2"
-            let mut map_data = line.split_whitespace();
-            water_light.push(AlmanacMap {
-                destination_start: map_data
-                    .next()
-                    .expect("Failed to get soil")
-                    .trim()
-                    .parse()
-                    .expect("Failed to parse soil"),
-                source_start: map_data
-                    .next()
-                    .expect("Failed to get seed")
-                    .trim()
-                    .parse()
-                    .expect("Failed to parse seed"),
-                range_length: map_data
-                    .next()
-                    .expect("Failed to get range length")
-                    .trim()
-                    .parse()
-                    .expect("Failed to parse range length"),
-            });
+            let map = current.as_ref()
+                .and_then(|source| maps.get_mut(source))
+                .ok_or_else(|| format!("Line {}: map rule before any map header", number + 1))?;
+            let values = line.split_whitespace()
+                .map(|value| value.parse::<i64>().map_err(|e| format!("Line {}: failed to parse '{}': {}", number + 1, value, e)))
+                .collect::<Result<Vec<_>, _>>()?;
+            let [destination_start, source_start, range_length] = values[..] else {
+                return Err(format!("Line {}: map rules need a destination, source and length", number + 1));
+            };
+            map.rules.push(AlmanacMap { source_start, destination_start, range_length });
         }
 
-        // Get light_temperature
-        let mut found = false;
-        while let Some(line) = lines.next() {
-            let line = line.expect("Failed to read light_temperature line");
+        for map in maps.values_mut() {
+            map.rules.sort_by_key(|rule| rule.source_start);
+        }
 
-            if line.is_empty() {
-                if !found {
-                    continue; // Whitespace before
-                } else {
-                    break; // Whitespace after
-                }
-            }
+        Ok(Almanac { seeds: Vec::new(), seed_values, maps })
+    }
+}
 
-            if line.starts_with("light-to-temperature map:") {
-                found = true;
-                continue;
+// Read the "seeds:" numbers as individual seeds or as start and length pairs
+fn seed_ranges(values: &[i64], seed_mode: SeedMode) -> Result<Vec<Range>, String> {
+    match seed_mode {
+        SeedMode::Individual => Ok(values.iter().map(|&seed| Range { start: seed, end: seed }).collect()),
+        SeedMode::Ranges => values.chunks(2).map(|seed_pair| {
+            let [start, length] = *seed_pair else {
+                return Err("Seeds should come in start and length pairs".to_string());
+            };
+            if length <= 0 {
+                return Err(format!("Seed range starting at {} has length {}, expected at least 1", start, length));
             }
+            let end = start.checked_add(length - 1)
+                .ok_or_else(|| format!("Seed range starting at {} with length {} goes past {}", start, length, i64::MAX))?;
+            Ok(Range { start, end })
+        }).collect(),
+    }
+}
 
-            //Create AlmanacMap from a line like "50 98 2"
-            let mut map_data = line.split_whitespace();
-            light_temperature.push(AlmanacMap {
-                destination_start: map_data
-                    .next()
-                    .expect("Failed to get soil")
-                    .trim()
-                    .parse()
-                    .expect("Failed to parse soil"),
-                source_start: map_data
-                    .next()
-                    .expect("Failed to get seed")
-                    .trim()
-                    .parse()
-                    .expect("Failed to parse seed"),
-                range_length: map_data
-                    .next()
-                    .expect("Failed to get range length")
-                    .trim()
-                    .parse()
-                    .expect("Failed to parse range length"),
-            });
-        }
-
-        // Get temperature_humidity
-        let mut found = false;
-        while let Some(line) = lines.next() {
-            let line = line.expect("Failed to read temperature_humidity line");
+fn lowest_location(almanac: &Almanac, seeds: Vec<Range>) -> i64 {
+    let locations = almanac.map_ranges("seed", "location", seeds)
+        .unwrap_or_else(|e| panic!("Failed to map seeds to locations: {}", e));
 
-            if line.is_empty() {
-                if !found {
-                    continue; // Whitespace before
-                } else {
-                    break; // Whitespace after
-                }
-            }
+    locations
+        .iter()
+        .map(|range| range.start)
+        .min()
+        .expect("No seeds in almanac")
+}
 
-            if line.starts_with("temperature-to-humidity map:") {
-                found = true;
-                continue;
-            }
+// Parse "46" or "40-60" into a range
+fn parse_range(range: &str) -> Range {
+    let (start, end) = range.split_once('-').unwrap_or((range, range));
+    Range {
+        start: start.trim().parse().expect("Failed to parse range start"),
+        end: end.trim().parse().expect("Failed to parse range end"),
+    }
+}
 
-            //Create AlmanacMap from a line like "50 98 2"
-            let mut map_data = line.split_whitespace();
-            temperature_humidity.push(AlmanacMap {
-                destination_start: map_data
-                    .next()
-                    .expect("Failed to get soil")
-                    .trim()
-                    .parse()
-                    .expect("Failed to parse soil"),
-                source_start: map_data
-                    .next()
-                    .expect("Failed to get seed")
-                    .trim()
-                    .parse()
-                    .expect("Failed to parse seed"),
-                range_length: map_data
-                    .next()
-                    .expect("Failed to get range length")
-                    .trim()
-                    .parse()
-                    .expect("Failed to parse range length"),
-            });
+fn main() {
+    // Get file name (or "bench" or "lint") and options from command line
+    let args: Vec<String> = env::args().collect();
+    let mut filename = None;
+    let mut lint_file = None;
+    let mut table = false;
+    let mut inverse_table = false;
+    let mut reverse = Vec::new();
+    let mut lookups = Vec::new();
+    let mut rules = 50_000;
+    let mut seed_ranges = 20;
+
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--table" => table = true,
+            "--inverse-table" => inverse_table = true,
+            "--lookup" => lookups.push(args.next().expect("Please provide a seed after --lookup").parse::<i64>().expect("Failed to parse seed")),
+            "--rules" => rules = args.next().expect("Please provide a count after --rules").parse().expect("Failed to parse rule count"),
+            "--seed-ranges" => seed_ranges = args.next().expect("Please provide a count after --seed-ranges").parse().expect("Failed to parse seed range count"),
+            "--reverse" => reverse.push(parse_range(args.next().expect("Please provide a location or range after --reverse"))),
+            _ if filename.is_some_and(|command: &String| command == "lint") => lint_file = Some(arg),
+            _ => filename = Some(arg),
         }
+    }
+    let filename = filename.expect("Please provide a filename, 'bench' or 'lint <file>'");
 
-        // Get humidity_location
-        let mut found = false;
-        while let Some(line) = lines.next() {
-            let line = line.expect("Failed to read humidity_location line");
+    if filename == "bench" {
+        bench::run(rules, seed_ranges, 2023);
+        return;
+    }
+    if filename == "lint" {
+        let filename = lint_file.expect("Please provide a filename to lint");
+        let input = fs::read_to_string(filename).expect("Failed to read file");
+        let almanac = Almanac::parse(input.as_bytes()).expect("Failed to parse almanac");
+
+        let report = almanac.validate();
+        println!("{}", report);
+        if !report.errors.is_empty() {
+            std::process::exit(1);
+        }
+        return;
+    }
 
-            if line.is_empty() {
-                if !found {
-                    continue; // Whitespace before
-                } else {
-                    break; // Whitespace after
-                }
-            }
+    let input = fs::read_to_string(filename).expect("Failed to read file");
+    let almanac = Almanac::from_reader(input.as_bytes(), SeedMode::Individual).expect("Failed to parse almanac");
 
-            if line.starts_with("humidity-to-location map:") {
-                found = true;
-                continue;
-            }
+    println!("Minimum location for individual seeds: {}", lowest_location(&almanac, almanac.seeds.clone()));
 
-            //Create AlmanacMap from a line like "50 98 2"
-            let mut map_data = line.split_whitespace();
-            humidity_location.push(AlmanacMap {
-                destination_start: map_data
-                    .next()
-                    .expect("Failed to get soil")
-                    .trim()
-                    .parse()
-                    .expect("Failed to parse soil"),
-                source_start: map_data
-                    .next()
-                    .expect("Failed to get seed")
-                    .trim()
-                    .parse()
-                    .expect("Failed to parse seed"),
-                range_length: map_data
-                    .next()
-                    .expect("Failed to get range length")
-                    .trim()
-                    .parse()
-                    .expect("Failed to parse range length"),
-            });
-        }
+    let seed_ranges = almanac.seeds_as(SeedMode::Ranges).unwrap_or_else(|e| panic!("Failed to read seed ranges: {}", e));
+    println!("Minimum location for all seeds in Almanac: {}", lowest_location(&almanac, seed_ranges));
 
-        seed_soil.sort_by(|a, b| a.source_start.cmp(&b.source_start));
-        soil_fertilizer.sort_by(|a, b| a.source_start.cmp(&b.source_start));
-        fertilizer_water.sort_by(|a, b| a.source_start.cmp(&b.source_start));
-        water_light.sort_by(|a, b| a.source_start.cmp(&b.source_start));
-        light_temperature.sort_by(|a, b| a.source_start.cmp(&b.source_start));
-        temperature_humidity.sort_by(|a, b| a.source_start.cmp(&b.source_start));
-        humidity_location.sort_by(|a, b| a.source_start.cmp(&b.source_start));
-
-        Ok(Almanac {
-            seeds,
-            seed_soil,
-            soil_fertilizer,
-            fertilizer_water,
-            water_light,
-            light_temperature,
-            temperature_humidity,
-            humidity_location,
-        })
+    if !table && !inverse_table && reverse.is_empty() && lookups.is_empty() {
+        return;
     }
-}
 
-fn main() {
-    // Get file name from command line
-    // let args: Vec<String> = env::args().collect();
-    // let filename = args.get(1).expect("Please provide a filename");
-
-    let filename = "input/input2.txt";
-
-    let file = File::open(filename).expect("Failed to open file");
-    let reader = BufReader::new(file);
-
-    let almanac = Almanac::from_reader(reader).expect("Failed to parse almanac");
-
-    // let mut locations = Vec::new();
-
-    let seed_soil = almanac.traverse_almanac_map(almanac.seeds.clone(), AlmanacMapType::SeedSoil);
-    let soil_fertilizer = almanac.traverse_almanac_map(seed_soil, AlmanacMapType::SoilFertilizer);
-    let fertilizer_water =
-        almanac.traverse_almanac_map(soil_fertilizer, AlmanacMapType::FertilizerWater);
-    let water_light = almanac.traverse_almanac_map(fertilizer_water, AlmanacMapType::WaterLight);
-    let light_temperature =
-        almanac.traverse_almanac_map(water_light, AlmanacMapType::LightTemperature);
-    let temperature_humidity =
-        almanac.traverse_almanac_map(light_temperature, AlmanacMapType::TemperatureHumidity);
-    let humidity_location =
-        almanac.traverse_almanac_map(temperature_humidity, AlmanacMapType::HumidityLocation);
-
-    // locations.push(humidity_location);
-
-    println!(
-        "Minimum location for all seeds in Almanac: {:?}",
-        humidity_location
-            .iter()
-            .map(|range| range.start)
-            .min()
-            .unwrap()
-    );
+    let composed = almanac.compose("seed", "location")
+        .unwrap_or_else(|e| panic!("Failed to compose seed to location: {}", e));
+
+    if table {
+        println!("Seed to location ({} breakpoints):", composed.breakpoints().len());
+        print!("{}", composed);
+    }
+    if inverse_table {
+        match composed.invert() {
+            Ok(inverse) => {
+                println!("Location to seed:");
+                print!("{}", inverse);
+            },
+            Err(e) => println!("Cannot invert seed to location: {}", e),
+        }
+    }
+    for seed in lookups {
+        match composed.apply(seed) {
+            Some(location) => println!("Seed {} is at location {}", seed, location),
+            None => println!("Seed {} has no location", seed),
+        }
+    }
+    if !reverse.is_empty() {
+        let seeds = almanac.reverse_ranges("seed", "location", &reverse)
+            .unwrap_or_else(|e| panic!("Failed to reverse locations: {}", e));
+        println!("Seeds reaching those locations:");
+        for range in seeds {
+            println!("  {} to {}", range.start, range.end);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -604,162 +413,159 @@ humidity-to-location map:
 "
     }
 
+    fn rules(map: &[(i64, i64, i64)]) -> Vec<AlmanacMap<i64, i64>> {
+        map.iter()
+            .map(|&(destination_start, source_start, range_length)| AlmanacMap { source_start, destination_start, range_length })
+            .collect()
+    }
+
     #[test]
     fn test_from_reader() {
         let input = test_data();
         let reader = input.as_bytes();
-        let result = Almanac::from_reader(reader).unwrap();
+        let result = Almanac::from_reader(reader, SeedMode::Ranges).unwrap();
 
         assert_eq!(
             result.seeds,
             vec![Range { start: 79, end: 92 }, Range { start: 55, end: 56 }]
         );
 
-        let seed_soil = vec![
-            AlmanacMap {
-                source_start: 98,
-                destination_start: 50,
-                range_length: 2,
-            },
-            AlmanacMap {
-                source_start: 50,
-                destination_start: 52,
-                range_length: 48,
-            },
+        // Rules are sorted by source so traversal can pass gaps through
+        let expected = [
+            ("seed", "soil", rules(&[(52, 50, 48), (50, 98, 2)])),
+            ("soil", "fertilizer", rules(&[(0, 15, 37), (37, 52, 2)])),
+            ("fertilizer", "water", rules(&[(0, 11, 42), (49, 53, 8)])),
+            ("water", "light", rules(&[(88, 18, 7)])),
+            ("light", "temperature", rules(&[(45, 77, 23)])),
+            ("temperature", "humidity", rules(&[(0, 69, 1)])),
+            ("humidity", "location", rules(&[(60, 56, 37)])),
         ];
-        assert_eq!(result.seed_soil, seed_soil);
+        assert_eq!(result.maps.len(), expected.len());
+        for (source, destination, rules) in expected {
+            assert_eq!(result.maps[source], CategoryMap {
+                source: source.to_string(),
+                destination: destination.to_string(),
+                index: PiecewiseMap::from_rules(&rules).unwrap(),
+                rules,
+            });
+        }
+    }
 
-        let soil_fertilizer = vec![
-            AlmanacMap {
-                source_start: 15,
-                destination_start: 0,
-                range_length: 37,
-            },
-            AlmanacMap {
-                source_start: 52,
-                destination_start: 37,
-                range_length: 2,
-            },
-        ];
-        assert_eq!(result.soil_fertilizer, soil_fertilizer);
+    #[test]
+    fn test_from_reader_any_order() {
+        let input = "
+seeds: 1 1
 
-        let fertilizer_water = vec![
-            AlmanacMap {
-                source_start: 53,
-                destination_start: 49,
-                range_length: 8,
-            },
-            AlmanacMap {
-                source_start: 11,
-                destination_start: 0,
-                range_length: 42,
-            },
-        ];
-        assert_eq!(result.fertilizer_water, fertilizer_water);
-
-        let water_light = vec![AlmanacMap {
-            source_start: 18,
-            destination_start: 88,
-            range_length: 7,
-        }];
-        assert_eq!(result.water_light, water_light);
-
-        let light_temperature = vec![AlmanacMap {
-            source_start: 77,
-            destination_start: 45,
-            range_length: 23,
-        }];
-        assert_eq!(result.light_temperature, light_temperature);
-
-        let temperature_humidity = vec![AlmanacMap {
-            source_start: 69,
-            destination_start: 0,
-            range_length: 1,
-        }];
-        assert_eq!(result.temperature_humidity, temperature_humidity);
-
-        let humidity_location = vec![AlmanacMap {
-            source_start: 56,
-            destination_start: 60,
-            range_length: 37,
-        }];
-        assert_eq!(result.humidity_location, humidity_location);
-    }
-
-    // #[test]
-    // fn test_get_valid_dest() {
-    //     let almanac_map = AlmanacMap {
-    //         source_start: 10,
-    //         destination_start: 100,
-    //         range_length: 5,
-    //     };
-
-    //     assert_eq!(almanac_map.get_dest(Range{start:10,end:14}), (Some(vec![Range{start:100,end:104}]), None));
-    //     assert_eq!(almanac_map.get_dest(Range{start:11,end:13}), (Some(vec![Range{start:101,end:103}]), None));
-    //     assert_eq!(almanac_map.get_dest(Range{start:5,end:9}), (None, Some(vec![Range{start:5,end:9}])));
-    //     assert_eq!(almanac_map.get_dest(Range{start:15,end:16}), (None, Some(vec![Range{start:15,end:16}])));
-    //     assert_eq!(almanac_map.get_dest(Range{start:5,end:14}), (Some(vec![Range{start:100,end:104}]), Some(vec![Range{start:5,end:9}])));
-    //     assert_eq!(almanac_map.get_dest(Range{start:10,end:16}), (Some(vec![Range{start:100,end:104}]), Some(vec![Range{start:15,end:16}])));
-    // }
-
-    // #[test]
-    // fn test_get_invalid_dest() {
-    //     let almanac_map = AlmanacMap {
-    //         source_start: 10,
-    //         destination_start: 100,
-    //         range_length: 5,
-    //     };
-
-    //     assert_eq!(almanac_map.get_dest(9), None);
-    //     assert_eq!(almanac_map.get_dest(15), None);
-    //     assert_eq!(almanac_map.get_dest(20), None);
-    // }
-
-    //     #[test]
-    //     fn test_traverse_almanac_map_single() {
-    //         let input =
-    // "
-    // seeds: 1
-
-    // seed-to-soil map:
-    // 10 20 5
-    // ";
-    //         let reader = input.as_bytes();
-    //         let almanac = Almanac::from_reader(reader).unwrap();
-
-    //         let result = almanac.traverse_almanac_map(vec![10], AlmanacMapType::SeedSoil);
-    //         assert_eq!(result, vec![20]);
-
-    //         let result = almanac.traverse_almanac_map(vec![11], AlmanacMapType::SeedSoil);
-    //         assert_eq!(result, vec![21]);
-
-    //         let result = almanac.traverse_almanac_map(vec![14], AlmanacMapType::SeedSoil);
-    //         assert_eq!(result, vec![24]);
-
-    //         let result = almanac.traverse_almanac_map(vec![15], AlmanacMapType::SeedSoil);
-    //         assert_eq!(result, vec![]);
-    //     }
-
-    //     #[test]
-    //     fn test_traverse_almanac_map_multiple() {
-    //         let input =
-    // "
-    // seeds: 1
-
-    // seed-to-soil map:
-    // 10 20 5
-    // 10 30 5
-    // ";
-    //         let reader = input.as_bytes();
-    //         let almanac = Almanac::from_reader(reader).unwrap();
-
-    //         let result = almanac.traverse_almanac_map(vec![10], AlmanacMapType::SeedSoil);
-    //         assert_eq!(result, vec![20, 30]);
-
-    //         let result = almanac.traverse_almanac_map(vec![11, 12], AlmanacMapType::SeedSoil);
-    //         assert_eq!(result, vec![21, 22, 31, 32]);
-
-    //         let result = almanac.traverse_almanac_map(vec![09, 15], AlmanacMapType::SeedSoil);
-    //         assert_eq!(result, vec![]);
-    //     }
+b-to-c map:
+10 0 5
+
+a-to-b map:
+0 5 5
+";
+        let almanac = Almanac::from_reader(input.as_bytes(), SeedMode::Ranges).unwrap();
+
+        assert_eq!(almanac.maps.len(), 2);
+        assert_eq!(almanac.map_ranges("a", "c", vec![Range { start: 5, end: 6 }]), Ok(vec![Range { start: 10, end: 11 }]));
+    }
+
+    #[test]
+    fn test_from_reader_errors() {
+        assert!(Almanac::from_reader("a-to-b map:\n1 2\n".as_bytes(), SeedMode::Ranges).is_err());
+        assert!(Almanac::from_reader("1 2 3\n".as_bytes(), SeedMode::Ranges).is_err());
+        assert!(Almanac::from_reader("a-b map:\n".as_bytes(), SeedMode::Ranges).is_err());
+        assert!(Almanac::from_reader("a-to-b map:\na-to-c map:\n".as_bytes(), SeedMode::Ranges).is_err());
+        assert!(Almanac::from_reader("seeds: 1 2 3\n".as_bytes(), SeedMode::Ranges).is_err());
+        assert!(Almanac::from_reader("a-to-b map:\n0 9223372036854775807 2\n".as_bytes(), SeedMode::Ranges).is_err());
+    }
+
+    #[test]
+    fn test_seed_modes() {
+        let individual = Almanac::from_reader(test_data().as_bytes(), SeedMode::Individual).unwrap();
+        assert_eq!(individual.seeds, vec![
+            Range { start: 79, end: 79 },
+            Range { start: 14, end: 14 },
+            Range { start: 55, end: 55 },
+            Range { start: 2, end: 2 },
+        ]);
+
+        assert_eq!(lowest_location(&individual, individual.seeds.clone()), 2);
+        assert_eq!(lowest_location(&individual, individual.seeds_as(SeedMode::Ranges).unwrap()), 49);
+        assert!(Almanac::from_reader("seeds: 1 2 3".as_bytes(), SeedMode::Individual).is_ok());
+
+        assert_eq!(seed_ranges(&[5, 0], SeedMode::Ranges), Err("Seed range starting at 5 has length 0, expected at least 1".to_string()));
+        assert!(seed_ranges(&[5, -2], SeedMode::Ranges).is_err());
+        assert!(seed_ranges(&[i64::MAX, 2], SeedMode::Ranges).is_err());
+        assert_eq!(seed_ranges(&[i64::MAX, 1], SeedMode::Ranges), Ok(vec![Range { start: i64::MAX, end: i64::MAX }]));
+    }
+
+    #[test]
+    fn test_map_ranges() {
+        let almanac = Almanac::from_reader(test_data().as_bytes(), SeedMode::Ranges).unwrap();
+
+        let locations = almanac.map_ranges("seed", "location", almanac.seeds.clone()).unwrap();
+        assert_eq!(locations.iter().map(|range| range.start).min(), Some(49));
+
+        // Soil 81 is fertilizer 81, water 81, light 81, temperature 49, humidity 49
+        assert_eq!(almanac.map_ranges("soil", "humidity", vec![Range { start: 81, end: 81 }]), Ok(vec![Range { start: 49, end: 49 }]));
+        assert_eq!(almanac.map_ranges("water", "water", vec![Range { start: 3, end: 4 }]), Ok(vec![Range { start: 3, end: 4 }]));
+    }
+
+    #[test]
+    fn test_traverse_almanac_map() {
+        let index = PiecewiseMap::from_rules(&rules(&[(10, 0, 5), (0, 5, 5)])).unwrap();
+
+        // A range starting just past a rule doesn't leave an empty range behind
+        assert_eq!(Almanac::traverse_almanac_map(vec![Range { start: 5, end: 5 }], &index), vec![Range { start: 0, end: 0 }]);
+        // Pieces that land next to each other are merged
+        assert_eq!(
+            Almanac::traverse_almanac_map(vec![Range { start: 3, end: 6 }, Range { start: 8, end: 12 }], &index),
+            vec![Range { start: 0, end: 1 }, Range { start: 3, end: 4 }, Range { start: 10, end: 14 }]
+        );
+        assert_eq!(Almanac::traverse_almanac_map(vec![], &index), vec![]);
+    }
+
+    #[test]
+    fn test_compose() {
+        let almanac = Almanac::from_reader(test_data().as_bytes(), SeedMode::Ranges).unwrap();
+        let composed = almanac.compose("seed", "location").unwrap();
+
+        for seed in 0..120 {
+            let locations = almanac.map_ranges("seed", "location", vec![Range { start: seed, end: seed }]).unwrap();
+            assert_eq!(locations.len(), 1);
+            assert!(locations[0].start <= locations[0].end);
+            assert_eq!(composed.apply(seed), Some(locations[0].start));
+        }
+        assert_eq!(composed.apply(79), Some(49));
+        assert_eq!(composed.breakpoints()[0], i64::MIN);
+    }
+
+    #[test]
+    fn test_reverse_ranges() {
+        let almanac = Almanac::from_reader(test_data().as_bytes(), SeedMode::Ranges).unwrap();
+
+        let seeds = almanac.reverse_ranges("seed", "location", &[Range { start: 49, end: 49 }]).unwrap();
+        assert!(seeds.contains(&Range { start: 79, end: 79 }));
+        for range in &seeds {
+            for seed in range.start..=range.end {
+                assert_eq!(almanac.compose("seed", "location").unwrap().apply(seed), Some(49));
+            }
+        }
+        assert_eq!(almanac.reverse_ranges("seed", "colour", &[]), Err(AlmanacError::UnknownCategory("colour".to_string())));
+    }
+
+    #[test]
+    fn test_chain_errors() {
+        let almanac = Almanac::from_reader(test_data().as_bytes(), SeedMode::Ranges).unwrap();
+
+        assert_eq!(almanac.chain("seed", "colour").unwrap_err(), AlmanacError::UnknownCategory("colour".to_string()));
+        assert_eq!(almanac.chain("location", "seed").unwrap_err(), AlmanacError::Unreachable {
+            from: "location".to_string(),
+            to: "seed".to_string(),
+            end: "location".to_string(),
+        });
+
+        let cyclic = Almanac::from_reader("a-to-b map:\nb-to-a map:\nc-to-d map:\n".as_bytes(), SeedMode::Ranges).unwrap();
+        assert_eq!(cyclic.chain("a", "d").unwrap_err(), AlmanacError::Cycle(vec!["a".to_string(), "b".to_string(), "a".to_string()]));
+        assert_eq!(cyclic.chain("a", "d").unwrap_err().to_string(), "Maps form a cycle: a -> b -> a");
+    }
 }
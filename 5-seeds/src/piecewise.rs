@@ -0,0 +1,275 @@
+use std::fmt;
+
+use crate::{merge_ranges, AlmanacMap, Range};
+
+// Values from `start` to `end` inclusive map to themselves plus `offset`
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Piece {
+    pub start: i64,
+    pub end: i64,
+    pub offset: i64,
+}
+
+// A piecewise-linear function made of sorted, non-overlapping pieces. Values
+// outside every piece have no image. Adjacent pieces always have different
+// offsets, so the piece starts are exactly the breakpoints. Every piece's
+// image fits in an i64; anything that would overflow is an error instead.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct PiecewiseMap {
+    pub pieces: Vec<Piece>,
+}
+
+fn overflow(start: i64, end: i64, offset: i64) -> String {
+    format!("Moving values {} to {} by {:+} overflows", start, end, offset)
+}
+
+impl Piece {
+    fn image(&self) -> Result<Range, String> {
+        match (self.start.checked_add(self.offset), self.end.checked_add(self.offset)) {
+            (Some(start), Some(end)) => Ok(Range { start, end }),
+            _ => Err(overflow(self.start, self.end, self.offset)),
+        }
+    }
+
+    // The sources of the values `start` to `end`, which must lie in the image
+    fn sources(&self, start: i64, end: i64) -> Result<Range, String> {
+        match (start.checked_sub(self.offset), end.checked_sub(self.offset)) {
+            (Some(start), Some(end)) => Ok(Range { start, end }),
+            _ => Err(format!("Moving values {} to {} back by {:+} overflows", start, end, self.offset)),
+        }
+    }
+}
+
+impl PiecewiseMap {
+    // Every value maps to itself
+    pub fn identity() -> PiecewiseMap {
+        PiecewiseMap { pieces: vec![Piece { start: i64::MIN, end: i64::MAX, offset: 0 }] }
+    }
+
+    // The function described by one map's rules over every value, where
+    // anything not covered by a rule maps to itself. Where rules overlap
+    // the one starting first wins.
+    pub fn from_rules(rules: &[AlmanacMap<i64, i64>]) -> Result<PiecewiseMap, String> {
+        let mut rules: Vec<&AlmanacMap<i64, i64>> = rules.iter().filter(|rule| rule.range_length > 0).collect();
+        rules.sort_by_key(|rule| rule.source_start);
+
+        let mut pieces = Vec::new();
+        let mut next = i64::MIN;
+
+        for rule in rules {
+            let start = rule.source_start.max(next);
+            let end = rule.source_start.checked_add(rule.range_length - 1)
+                .ok_or_else(|| format!("Rule starting at {} with length {} goes past {}", rule.source_start, rule.range_length, i64::MAX))?;
+            if end < start {
+                continue;
+            }
+            if start > next {
+                pieces.push(Piece { start: next, end: start - 1, offset: 0 });
+            }
+            let offset = rule.destination_start.checked_sub(rule.source_start)
+                .ok_or_else(|| format!("Rule moving {} to {} has an offset outside i64", rule.source_start, rule.destination_start))?;
+            let piece = Piece { start, end, offset };
+            piece.image()?;
+            pieces.push(piece);
+
+            if end == i64::MAX {
+                return Ok(PiecewiseMap::normalised(pieces));
+            }
+            next = end + 1;
+        }
+        pieces.push(Piece { start: next, end: i64::MAX, offset: 0 });
+
+        Ok(PiecewiseMap::normalised(pieces))
+    }
+
+    // Sort the pieces and merge neighbours that continue the same line
+    fn normalised(mut pieces: Vec<Piece>) -> PiecewiseMap {
+        pieces.sort_by_key(|piece| piece.start);
+
+        let mut merged: Vec<Piece> = Vec::with_capacity(pieces.len());
+        for piece in pieces {
+            match merged.last_mut() {
+                Some(last) if last.offset == piece.offset && last.end.checked_add(1) == Some(piece.start) => last.end = piece.end,
+                _ => merged.push(piece),
+            }
+        }
+
+        PiecewiseMap { pieces: merged }
+    }
+
+    // The image of `value`, or None if no piece covers it. Piece images are
+    // checked when the map is built, so the addition can't overflow.
+    pub fn apply(&self, value: i64) -> Option<i64> {
+        let index = self.pieces.partition_point(|piece| piece.end < value);
+        self.pieces.get(index)
+            .filter(|piece| piece.start <= value)
+            .and_then(|piece| value.checked_add(piece.offset))
+    }
+
+    // This function followed by `next`
+    pub fn then(&self, next: &PiecewiseMap) -> Result<PiecewiseMap, String> {
+        let mut pieces = Vec::new();
+
+        for piece in &self.pieces {
+            let image = piece.image()?;
+            let first = next.pieces.partition_point(|later| later.end < image.start);
+
+            for later in next.pieces[first..].iter().take_while(|later| later.start <= image.end) {
+                let sources = piece.sources(image.start.max(later.start), image.end.min(later.end))?;
+                let offset = piece.offset.checked_add(later.offset)
+                    .ok_or_else(|| overflow(sources.start, sources.end, piece.offset))?;
+                pieces.push(Piece { start: sources.start, end: sources.end, offset });
+            }
+        }
+
+        Ok(PiecewiseMap::normalised(pieces))
+    }
+
+    // The inverse function, if no value is reached from two places
+    pub fn invert(&self) -> Result<PiecewiseMap, String> {
+        let mut pieces: Vec<Piece> = self.pieces.iter()
+            .map(|piece| {
+                let image = piece.image()?;
+                let offset = piece.offset.checked_neg().ok_or_else(|| overflow(image.start, image.end, piece.offset))?;
+                Ok(Piece { start: image.start, end: image.end, offset })
+            })
+            .collect::<Result<_, String>>()?;
+        pieces.sort_by_key(|piece| piece.start);
+
+        for pair in pieces.windows(2) {
+            if pair[1].start <= pair[0].end {
+                return Err(format!(
+                    "Values {} to {} are reached from more than one source",
+                    pair[1].start, pair[0].end.min(pair[1].end)
+                ));
+            }
+        }
+
+        Ok(PiecewiseMap::normalised(pieces))
+    }
+
+    // Every source range that maps into one of `ranges`, sorted and merged
+    pub fn preimage(&self, ranges: &[Range]) -> Result<Vec<Range>, String> {
+        let mut sources: Vec<Range> = Vec::new();
+
+        for piece in &self.pieces {
+            let image = piece.image()?;
+            for range in ranges {
+                let start = image.start.max(range.start);
+                let end = image.end.min(range.end);
+                if start <= end {
+                    sources.push(piece.sources(start, end)?);
+                }
+            }
+        }
+
+        Ok(merge_ranges(sources))
+    }
+
+    pub fn breakpoints(&self) -> Vec<i64> {
+        self.pieces.iter().map(|piece| piece.start).collect()
+    }
+}
+
+impl fmt::Display for PiecewiseMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bound = |value: i64| match value {
+            i64::MIN => "min".to_string(),
+            i64::MAX => "max".to_string(),
+            _ => value.to_string(),
+        };
+
+        writeln!(f, "{:>20} {:>20} {:>20} {:>20} {:>12}", "from start", "from end", "to start", "to end", "offset")?;
+        for piece in &self.pieces {
+            let image = piece.image().map_err(|_| fmt::Error)?;
+            writeln!(
+                f, "{:>20} {:>20} {:>20} {:>20} {:>+12}",
+                bound(piece.start), bound(piece.end), bound(image.start), bound(image.end), piece.offset
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(destination_start: i64, source_start: i64, range_length: i64) -> AlmanacMap<i64, i64> {
+        AlmanacMap { source_start, destination_start, range_length }
+    }
+
+    #[test]
+    fn test_from_rules() {
+        let map = PiecewiseMap::from_rules(&[rule(50, 98, 2), rule(52, 50, 48)]).unwrap();
+
+        assert_eq!(map.pieces, vec![
+            Piece { start: i64::MIN, end: 49, offset: 0 },
+            Piece { start: 50, end: 97, offset: 2 },
+            Piece { start: 98, end: 99, offset: -48 },
+            Piece { start: 100, end: i64::MAX, offset: 0 },
+        ]);
+        assert_eq!(map.breakpoints(), vec![i64::MIN, 50, 98, 100]);
+        assert_eq!(map.apply(79), Some(81));
+        assert_eq!(map.apply(99), Some(51));
+        assert_eq!(map.apply(-1), Some(-1));
+
+        // A rule that keeps values in place merges with the identity around it
+        let map = PiecewiseMap::from_rules(&[rule(10, 10, 5)]).unwrap();
+        assert_eq!(map.pieces, vec![Piece { start: i64::MIN, end: i64::MAX, offset: 0 }]);
+    }
+
+    #[test]
+    fn test_then() {
+        let first = PiecewiseMap::from_rules(&[rule(10, 0, 5)]).unwrap();
+        let second = PiecewiseMap::from_rules(&[rule(0, 12, 10)]).unwrap();
+        let composed = first.then(&second).unwrap();
+
+        for value in 0..40 {
+            assert_eq!(composed.apply(value), first.apply(value).and_then(|v| second.apply(v)));
+        }
+        assert_eq!(composed.apply(3), Some(1));
+        assert_eq!(composed.apply(0), Some(10));
+    }
+
+    #[test]
+    fn test_invert() {
+        let map = PiecewiseMap::from_rules(&[rule(50, 98, 2), rule(52, 50, 48)]).unwrap();
+        let inverse = map.invert().unwrap();
+
+        for value in 0..200 {
+            assert_eq!(map.apply(value).and_then(|v| inverse.apply(v)), Some(value));
+        }
+
+        // 5 is reached both from 0 and from itself
+        let collision = PiecewiseMap::from_rules(&[rule(5, 0, 1)]).unwrap();
+        assert_eq!(collision.invert(), Err("Values 5 to 5 are reached from more than one source".to_string()));
+    }
+
+    #[test]
+    fn test_preimage() {
+        let map = PiecewiseMap::from_rules(&[rule(5, 0, 1)]).unwrap();
+
+        assert_eq!(map.preimage(&[Range { start: 5, end: 5 }]), Ok(vec![Range { start: 0, end: 0 }, Range { start: 5, end: 5 }]));
+        assert_eq!(map.preimage(&[Range { start: 0, end: 3 }]), Ok(vec![Range { start: 1, end: 3 }]));
+        assert_eq!(map.preimage(&[Range { start: 4, end: 6 }]), Ok(vec![Range { start: 0, end: 0 }, Range { start: 4, end: 6 }]));
+    }
+
+    #[test]
+    fn test_overflow() {
+        // Offsets and images that don't fit in an i64 are errors rather than wrapping
+        assert!(PiecewiseMap::from_rules(&[rule(i64::MAX, -1, 1)]).is_err());
+        assert!(PiecewiseMap::from_rules(&[rule(i64::MAX - 1, 0, 5)]).is_err());
+        assert!(PiecewiseMap::from_rules(&[rule(0, i64::MAX, 2)]).is_err());
+        assert!(PiecewiseMap::from_rules(&[rule(i64::MAX - 4, 0, 5)]).is_ok());
+
+        // Each step fits, but i64::MIN would have to move by 2^64 - 1 overall
+        let up = PiecewiseMap::from_rules(&[rule(-1, i64::MIN, 1)]).unwrap();
+        let up_again = PiecewiseMap::from_rules(&[rule(i64::MAX - 1, -1, 1)]).unwrap();
+        assert!(up.then(&up_again).is_err());
+
+        let shift = PiecewiseMap::from_rules(&[rule(i64::MIN, 0, 1)]).unwrap();
+        assert_eq!(shift.apply(0), Some(i64::MIN));
+        assert!(shift.invert().is_err());
+    }
+}
@@ -0,0 +1,377 @@
+use std::fmt;
+
+use crate::{merge_ranges, Almanac, AlmanacMap, CategoryMap, Range};
+
+// A rule as written in the almanac: destination, source and length
+pub type RuleText = (i64, i64, i64);
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum ValidationError {
+    // A rule with a length of zero or less, which maps nothing
+    EmptyRule { map: String, rule: RuleText },
+    // A rule whose sources, destinations or offset don't fit in an i64
+    Overflow { map: String, rule: RuleText },
+    // Two rules both claim the same source values
+    Overlap { map: String, first: RuleText, second: RuleText, values: Range },
+    // Destination values reached by more than one rule
+    Collision { map: String, values: Range },
+}
+
+// The source values each map leaves unchanged from zero upwards, including
+// the open-ended tail above its last rule, and the destinations a rule shares
+// with those unchanged values. Sharing with the identity is common in real
+// almanacs, so it's only a warning.
+#[derive(PartialEq, Eq, Debug)]
+pub struct StageReport {
+    pub map: String,
+    pub identity_gaps: Vec<Range>,
+    pub identity_collisions: Vec<Range>,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct ValidationReport {
+    pub stages: Vec<StageReport>,
+    pub errors: Vec<ValidationError>,
+}
+
+fn rule_text(rule: &AlmanacMap<i64, i64>) -> RuleText {
+    (rule.destination_start, rule.source_start, rule.range_length)
+}
+
+fn overflows(rule: &AlmanacMap<i64, i64>) -> bool {
+    let last = rule.range_length - 1;
+    rule.source_start.checked_add(last).is_none()
+        || rule.destination_start.checked_add(last).is_none()
+        || rule.destination_start.checked_sub(rule.source_start).is_none()
+}
+
+// Overflowing rules are reported, then clamped at i64::MAX so the other
+// checks still see them
+fn rule_end(rule: &AlmanacMap<i64, i64>) -> i64 {
+    rule.source_start.saturating_add(rule.range_length - 1)
+}
+
+fn rule_image(rule: &AlmanacMap<i64, i64>) -> Range {
+    Range { start: rule.destination_start, end: rule.destination_start.saturating_add(rule.range_length - 1) }
+}
+
+// Parts of sorted, non-overlapping `ranges` that fall inside any of sorted,
+// non-overlapping `within`
+fn intersections(ranges: &[Range], within: &[Range]) -> Vec<Range> {
+    let mut found = Vec::new();
+    for range in ranges {
+        let first = within.partition_point(|other| other.end < range.start);
+        for other in within[first..].iter().take_while(|other| other.start <= range.end) {
+            found.push(Range { start: range.start.max(other.start), end: range.end.min(other.end) });
+        }
+    }
+    found
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rule = |(destination, source, length): &RuleText| format!("'{} {} {}'", destination, source, length);
+
+        match self {
+            ValidationError::EmptyRule { map, rule: text } => {
+                let problem = if text.2 == 0 { "zero" } else { "negative" };
+                write!(f, "{}: rule {} has {} length", map, rule(text), problem)
+            },
+            ValidationError::Overflow { map, rule: text } => write!(f, "{}: rule {} moves values outside i64", map, rule(text)),
+            ValidationError::Overlap { map, first, second, values } => write!(
+                f, "{}: rules {} and {} both map sources {} to {}", map, rule(first), rule(second), values.start, values.end
+            ),
+            ValidationError::Collision { map, values } => write!(
+                f, "{}: destinations {} to {} are reached from more than one source", map, values.start, values.end
+            ),
+        }
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for stage in &self.stages {
+            if stage.identity_gaps.is_empty() {
+                writeln!(f, "{}: no identity gaps", stage.map)?;
+            }
+            for gap in &stage.identity_gaps {
+                match gap.end {
+                    i64::MAX => writeln!(f, "{}: identity from {} upwards", stage.map, gap.start)?,
+                    end => writeln!(f, "{}: identity for {} to {}", stage.map, gap.start, end)?,
+                }
+            }
+            for values in &stage.identity_collisions {
+                writeln!(
+                    f, "warning: {}: destinations {} to {} are also reached by values that map to themselves",
+                    stage.map, values.start, values.end
+                )?;
+            }
+        }
+        for error in &self.errors {
+            writeln!(f, "error: {}", error)?;
+        }
+        write!(f, "{} error(s)", self.errors.len())
+    }
+}
+
+impl CategoryMap {
+    fn name(&self) -> String {
+        format!("{}-to-{}", self.source, self.destination)
+    }
+
+    fn validate(&self, errors: &mut Vec<ValidationError>) -> StageReport {
+        let map = self.name();
+
+        for rule in &self.rules {
+            if rule.range_length <= 0 {
+                errors.push(ValidationError::EmptyRule { map: map.clone(), rule: rule_text(rule) });
+            } else if overflows(rule) {
+                errors.push(ValidationError::Overflow { map: map.clone(), rule: rule_text(rule) });
+            }
+        }
+
+        // Rules are sorted by source, so each only needs checking against the
+        // earlier rule reaching furthest
+        let mut furthest: Option<&AlmanacMap<i64, i64>> = None;
+        let mut identity_gaps = Vec::new();
+        // The lowest value not yet covered, or None once a rule reaches i64::MAX
+        let mut next = Some(0);
+        for rule in self.rules.iter().filter(|rule| rule.range_length > 0) {
+            if let Some(earlier) = furthest {
+                let end = rule_end(earlier).min(rule_end(rule));
+                if rule.source_start <= end {
+                    errors.push(ValidationError::Overlap {
+                        map: map.clone(),
+                        first: rule_text(earlier),
+                        second: rule_text(rule),
+                        values: Range { start: rule.source_start, end },
+                    });
+                }
+            }
+            if furthest.is_none_or(|earlier| rule_end(rule) > rule_end(earlier)) {
+                furthest = Some(rule);
+            }
+
+            if let Some(start) = next.filter(|next| rule.source_start > *next) {
+                identity_gaps.push(Range { start, end: rule.source_start - 1 });
+            }
+            next = next.and_then(|next| rule_end(rule).checked_add(1).map(|after| after.max(next)));
+        }
+        if let Some(start) = next {
+            identity_gaps.push(Range { start, end: i64::MAX });
+        }
+
+        // Only rules are compared with each other, since values passing
+        // through unchanged landing on a rule's destinations is expected
+        let mut images: Vec<Range> = self.rules.iter()
+            .filter(|rule| rule.range_length > 0)
+            .map(rule_image)
+            .collect();
+        images.sort();
+
+        let mut collisions = Vec::new();
+        let mut reached = i64::MIN;
+        for (i, image) in images.iter().enumerate() {
+            if i > 0 && image.start <= reached {
+                collisions.push(Range { start: image.start, end: image.end.min(reached) });
+            }
+            reached = reached.max(image.end);
+        }
+        for values in merge_ranges(collisions) {
+            errors.push(ValidationError::Collision { map: map.clone(), values });
+        }
+
+        // The identity covers every value outside the rules, negative ones included
+        let covered = merge_ranges(self.rules.iter()
+            .filter(|rule| rule.range_length > 0)
+            .map(|rule| Range { start: rule.source_start, end: rule_end(rule) })
+            .collect());
+        let mut passed_through = Vec::new();
+        let mut start = Some(i64::MIN);
+        for rule in &covered {
+            if let Some(gap_start) = start.filter(|start| *start < rule.start) {
+                passed_through.push(Range { start: gap_start, end: rule.start - 1 });
+            }
+            start = rule.end.checked_add(1);
+        }
+        if let Some(gap_start) = start {
+            passed_through.push(Range { start: gap_start, end: i64::MAX });
+        }
+        let identity_collisions = intersections(&merge_ranges(images), &passed_through);
+
+        StageReport { map, identity_gaps, identity_collisions }
+    }
+}
+
+impl Almanac {
+    // Maps in the order they chain, starting from categories nothing maps to
+    fn stage_order(&self) -> Vec<&CategoryMap> {
+        let mut roots: Vec<&CategoryMap> = self.maps.values()
+            .filter(|map| !self.maps.values().any(|other| other.destination == map.source))
+            .collect();
+        roots.sort_by(|a, b| a.source.cmp(&b.source));
+
+        let mut order: Vec<&CategoryMap> = Vec::new();
+        for root in roots {
+            let mut current = Some(root);
+            while let Some(map) = current.filter(|map| !order.iter().any(|seen| seen.source == map.source)) {
+                order.push(map);
+                current = self.maps.get(&map.destination);
+            }
+        }
+
+        // Anything left is part of a cycle
+        let mut rest: Vec<&CategoryMap> = self.maps.values()
+            .filter(|map| !order.iter().any(|seen| seen.source == map.source))
+            .collect();
+        rest.sort_by(|a, b| a.source.cmp(&b.source));
+        order.extend(rest);
+        order
+    }
+
+    pub fn validate(&self) -> ValidationReport {
+        let mut errors = Vec::new();
+        let stages = self.stage_order().into_iter()
+            .map(|map| map.validate(&mut errors))
+            .collect();
+
+        ValidationReport { stages, errors }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SeedMode;
+
+    fn almanac(input: &str) -> Almanac {
+        Almanac::parse(input.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_valid_almanac() {
+        let report = almanac("
+seeds: 79 14
+
+seed-to-soil map:
+50 98 2
+52 50 48
+
+soil-to-fertilizer map:
+0 15 37
+37 52 2
+39 0 15
+").validate();
+
+        assert_eq!(report.errors, vec![]);
+        assert_eq!(report.stages, vec![
+            StageReport {
+                map: "seed-to-soil".to_string(),
+                identity_gaps: vec![Range { start: 0, end: 49 }, Range { start: 100, end: i64::MAX }],
+                identity_collisions: vec![],
+            },
+            StageReport {
+                map: "soil-to-fertilizer".to_string(),
+                identity_gaps: vec![Range { start: 54, end: i64::MAX }],
+                identity_collisions: vec![],
+            },
+        ]);
+        assert!(report.to_string().contains("seed-to-soil: identity from 100 upwards"));
+    }
+
+    #[test]
+    fn test_rule_errors() {
+        let report = almanac("
+a-to-b map:
+100 0 10
+200 5 10
+300 40 0
+400 50 -2
+").validate();
+
+        assert_eq!(report.errors[..3], [
+            ValidationError::EmptyRule { map: "a-to-b".to_string(), rule: (300, 40, 0) },
+            ValidationError::EmptyRule { map: "a-to-b".to_string(), rule: (400, 50, -2) },
+            ValidationError::Overlap {
+                map: "a-to-b".to_string(),
+                first: (100, 0, 10),
+                second: (200, 5, 10),
+                values: Range { start: 5, end: 9 },
+            },
+        ]);
+        assert_eq!(report.stages[0].identity_gaps, vec![Range { start: 15, end: i64::MAX }]);
+        assert_eq!(report.errors[0].to_string(), "a-to-b: rule '300 40 0' has zero length");
+        assert_eq!(report.errors[2].to_string(), "a-to-b: rules '100 0 10' and '200 5 10' both map sources 5 to 9");
+    }
+
+    #[test]
+    fn test_overflow() {
+        // Building the maps rejects these, but lint reads the raw rules
+        let input = "
+a-to-b map:
+0 9223372036854775800 100
+-9223372036854775808 10 5
+9223372036854775800 0 100
+";
+        assert!(Almanac::from_reader(input.as_bytes(), SeedMode::Ranges).is_err());
+
+        let report = almanac(input).validate();
+        let overflow = |rule| ValidationError::Overflow { map: "a-to-b".to_string(), rule };
+        assert_eq!(report.errors[..3], [
+            overflow((9223372036854775800, 0, 100)),
+            overflow((-9223372036854775808, 10, 5)),
+            overflow((0, 9223372036854775800, 100)),
+        ]);
+        assert_eq!(report.errors[0].to_string(), "a-to-b: rule '9223372036854775800 0 100' moves values outside i64");
+        assert_eq!(report.stages[0].identity_gaps, vec![Range { start: 100, end: 9223372036854775799 }]);
+    }
+
+    #[test]
+    fn test_collisions() {
+        // 0 to 4 and 20 to 24 both land on 10 to 14
+        let report = almanac("
+a-to-b map:
+10 0 5
+12 20 5
+").validate();
+
+        assert_eq!(report.errors, vec![ValidationError::Collision { map: "a-to-b".to_string(), values: Range { start: 12, end: 14 } }]);
+        assert_eq!(report.errors[0].to_string(), "a-to-b: destinations 12 to 14 are reached from more than one source");
+    }
+
+    #[test]
+    fn test_identity_collisions() {
+        // 0 to 4 land on 10 to 14, which also map to themselves
+        let report = almanac("
+a-to-b map:
+10 0 5
+").validate();
+
+        assert_eq!(report.errors, vec![]);
+        assert_eq!(report.stages[0].identity_collisions, vec![Range { start: 10, end: 14 }]);
+        assert!(report.to_string().contains(
+            "warning: a-to-b: destinations 10 to 14 are also reached by values that map to themselves"
+        ));
+
+        // Landing on values another rule moves away is fine
+        let report = almanac("
+a-to-b map:
+10 0 5
+0 10 5
+").validate();
+        assert_eq!(report.stages[0].identity_collisions, vec![]);
+    }
+
+    #[test]
+    fn test_stage_order() {
+        let report = almanac("
+c-to-d map:
+a-to-b map:
+b-to-c map:
+").validate();
+
+        let maps: Vec<&str> = report.stages.iter().map(|stage| stage.map.as_str()).collect();
+        assert_eq!(maps, vec!["a-to-b", "b-to-c", "c-to-d"]);
+        assert!(report.to_string().ends_with("0 error(s)"));
+    }
+}
@@ -1,107 +1,193 @@
+mod model;
+
 use std::{
     env,
-    fs::File,
-    io::{BufRead, BufReader, Error, ErrorKind}, num::ParseIntError, ops::Mul,
+    fs,
+    io::{BufRead, Error, ErrorKind, Lines},
 };
 
 #[derive(Debug)]
 struct Race {
-    total_time: u64,
-    record_distance: u64,
+    total_time: u128,
+    record_distance: u128,
 }
 
 impl TryFrom<(&str, &str)> for Race {
-    type Error = ParseIntError;
+    type Error = String;
 
     fn try_from(value: (&str, &str)) -> Result<Self, Self::Error> {
-        let total_time = value.0.parse()?;
-        let record_distance = value.1.parse()?;
+        let total_time = value.0.parse()
+            .map_err(|e| format!("Failed to parse time '{}': {}", value.0, e))?;
+        let record_distance = value.1.parse()
+            .map_err(|e| format!("Failed to parse distance '{}': {}", value.1, e))?;
 
         Ok(Race {total_time, record_distance})
     }
 }
 
 impl Race  {
-    fn get_min_winning_press(&self) -> u64 {
-        //=CEILING((total_time-SQRT(POW(total_time, 2)-4*record_distance))/2)
-        let total_time = self.total_time as f64;
-        let record_distance = self.record_distance as f64;
-
-        let winning_press = (total_time - (total_time.powi(2) - 4.0 * record_distance).sqrt()) / 2.0;
-
-        match winning_press.fract() == 0.0 {
-            true => winning_press as u64 + 1,
-            false => winning_press.ceil() as u64,
-        }
+    // Holding for `press` leaves total_time - press to travel at speed press.
+    // A product too big for u128 is certainly further than the record.
+    fn beats_record(&self, press: u128) -> bool {
+        press.checked_mul(self.total_time - press)
+            .is_none_or(|distance| distance > self.record_distance)
     }
 
-    fn get_max_winning_press(&self) -> u64 {
-        //=FLOOR((total_time+SQRT(POW(total_time, 2)-4*record_distance))/2)
-        let total_time = self.total_time as f64;
-        let record_distance = self.record_distance as f64;
-
-        let winning_press = (total_time + (total_time.powi(2) - 4.0 * record_distance).sqrt()) / 2.0;
+    fn get_min_winning_press(&self) -> Option<u128> {
+        // Distance rises up to half the time, so binary search that half for
+        // the first press beating the record
+        let half = self.total_time / 2;
+        if !self.beats_record(half) {
+            return None;
+        }
 
-        match winning_press.fract() == 0.0 {
-            true => winning_press as u64 - 1,
-            false => winning_press.floor() as u64,
+        let (mut low, mut high) = (0, half);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if self.beats_record(middle) {
+                high = middle;
+            } else {
+                low = middle + 1;
+            }
         }
+        Some(low)
     }
 
-    fn get_num_winning_presses(&self) -> u64 {
-        self.get_max_winning_press() - self.get_min_winning_press() + 1
+    fn get_max_winning_press(&self) -> Option<u128> {
+        // Distance is symmetric about half the time
+        self.get_min_winning_press().map(|press| self.total_time - press)
     }
 }
 
-fn define_races<R: BufRead>(reader: R) -> Result<Vec<Race>, Error> {
-    let mut lines = reader.lines();
-
-    let time_line = lines.next().expect("Failed to read line")?;
-    assert!(time_line.starts_with("Time:"));
+// How to read the numbers after each header
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum RaceSheetMode {
+    // Each column is a separate race
+    Columns,
+    // The spaces are bad kerning: all the digits form one race
+    Kerned,
+}
 
-    let distance_line = lines.next().expect("Failed to read line")?;
-    assert!(distance_line.starts_with("Distance:"));
+fn invalid_data(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
 
-    let time_values = time_line.split_whitespace().skip(1);
-    let distance_values = distance_line.split_whitespace().skip(1);
+// Read the line starting with `header` and return what follows it
+fn read_header_line<R: BufRead>(lines: &mut Lines<R>, header: &str) -> Result<String, Error> {
+    let line = lines.next()
+        .ok_or_else(|| invalid_data(format!("Missing '{}' line", header)))??;
 
-    let race_tuples = time_values.zip(distance_values);
+    match line.trim().strip_prefix(header) {
+        Some(values) => Ok(values.to_string()),
+        None => Err(invalid_data(format!("Expected a line starting with '{}', found '{}'", header, line))),
+    }
+}
 
-    match race_tuples.map(|r| r.try_into()).collect() {
-        Ok(races) => Ok(races),
-        Err(e) => Err(Error::new(ErrorKind::InvalidData, e)),
+// Join the digits of every value, failing if they don't fit in a u128
+fn kern(values: &str, name: &str) -> Result<String, Error> {
+    let digits: String = values.split_whitespace().collect();
+    match digits.parse::<u128>() {
+        Ok(_) => Ok(digits),
+        Err(e) => Err(invalid_data(format!("Kerned {} '{}' is not a valid number: {}", name, digits, e))),
     }
 }
 
+fn define_races<R: BufRead>(reader: R, mode: RaceSheetMode) -> Result<Vec<Race>, Error> {
+    let mut lines = reader.lines();
+
+    let time_line = read_header_line(&mut lines, "Time:")?;
+    let distance_line = read_header_line(&mut lines, "Distance:")?;
+
+    let race_tuples: Vec<(String, String)> = match mode {
+        RaceSheetMode::Columns => {
+            let time_values: Vec<&str> = time_line.split_whitespace().collect();
+            let distance_values: Vec<&str> = distance_line.split_whitespace().collect();
+            if time_values.len() != distance_values.len() {
+                return Err(invalid_data(format!(
+                    "Found {} times but {} distances", time_values.len(), distance_values.len()
+                )));
+            }
+            time_values.into_iter()
+                .zip(distance_values)
+                .map(|(time, distance)| (time.to_string(), distance.to_string()))
+                .collect()
+        },
+        RaceSheetMode::Kerned => vec![(kern(&time_line, "time")?, kern(&distance_line, "distance")?)],
+    };
+
+    race_tuples.iter()
+        .map(|(time, distance)| (time.as_str(), distance.as_str()).try_into().map_err(invalid_data))
+        .collect()
+}
+
 fn main() {
-    // Get file name from command line
+    // Get file name and options from command line
     let args: Vec<String> = env::args().collect();
-    let filename = args.get(1).expect("Please provide a filename");
+    let mut filename = None;
+    let mut model: Box<dyn model::BoatModel> = Box::new(model::Linear);
+
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--model" => {
+                let spec = args.next().expect("Please provide a model after --model");
+                model = model::parse_model(spec).unwrap_or_else(|e| panic!("{}", e));
+            },
+            _ => filename = Some(arg),
+        }
+    }
+    let filename = filename.expect("Please provide a filename");
+
+    let input = fs::read_to_string(filename).expect("Failed to read file");
 
-    let file = File::open(filename).expect("Failed to open file");
-    let reader = BufReader::new(file);
+    let races = define_races(input.as_bytes(), RaceSheetMode::Columns).unwrap_or_else(|e| {
+        eprintln!("Can't parse races: {}", e);
+        std::process::exit(1);
+    });
 
-    let races = define_races(reader).expect("Can't parse races");
+    let report = |race: &Race| race.report(model.as_ref()).unwrap_or_else(|e| {
+        eprintln!("Can't solve race: {}", e);
+        std::process::exit(1);
+    });
 
-    let num_winning_presses: Vec<u64> = races.iter().map(|r| r.get_num_winning_presses()).collect();
+    let reports: Vec<model::RaceReport> = races.iter().map(report).collect();
+    let num_winning_presses: Vec<u128> = reports.iter().map(|r| r.ways_to_win()).collect();
 
-    let ways_to_beat_record = num_winning_presses.iter().fold(1, Mul::mul);
+    let ways_to_beat_record: u128 = num_winning_presses.iter().product();
 
     println!("Number of winning presses: {:?}", num_winning_presses);
     println!("Ways to beat record: {:?}", ways_to_beat_record);
+
+    let kerned = define_races(input.as_bytes(), RaceSheetMode::Kerned).unwrap_or_else(|e| {
+        eprintln!("Can't parse kerned race: {}", e);
+        std::process::exit(1);
+    });
+    let kerned_report = report(&kerned[0]);
+    println!("Ways to beat the kerned race: {:?}", kerned_report.ways_to_win());
+
+    for (number, report) in reports.iter().enumerate() {
+        println!("Race {}: {}", number + 1, report);
+    }
+    println!("Kerned race: {}", kerned_report);
 }
 
 #[cfg(test)]
 mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
     use super::*;
 
+    // Counted the way main counts, through the linear model's report
+    fn ways_to_win(race: &Race) -> u128 {
+        race.report(&model::Linear).unwrap().ways_to_win()
+    }
+
     #[test]
     fn test_define_races() {
         let input = "Time:      7  15   30
 Distance:  9  40  200";
-        let reader = BufReader::new(input.as_bytes());
-
-        let races = define_races(reader).unwrap();
+        let races = define_races(input.as_bytes(), RaceSheetMode::Columns).unwrap();
 
         assert_eq!(races.len(), 3);
 
@@ -115,25 +201,54 @@ Distance:  9  40  200";
         assert_eq!(races[2].record_distance, 200);
     }
 
+    #[test]
+    fn test_define_kerned_race() {
+        let input = "Time:      7  15   30
+Distance:  9  40  200";
+        let races = define_races(input.as_bytes(), RaceSheetMode::Kerned).unwrap();
+
+        assert_eq!(races.len(), 1);
+        assert_eq!(races[0].total_time, 71530);
+        assert_eq!(races[0].record_distance, 940200);
+        assert_eq!(ways_to_win(&races[0]), 71503);
+
+        // 40 digits is more than a u128 holds
+        let input = format!("Time: {}\nDistance: 1", "1234567890 ".repeat(4));
+        let error = define_races(input.as_bytes(), RaceSheetMode::Kerned).unwrap_err();
+        assert!(error.to_string().starts_with("Kerned time"));
+    }
+
+    #[test]
+    fn test_define_races_errors() {
+        let error = |input: &str| define_races(input.as_bytes(), RaceSheetMode::Columns).unwrap_err().to_string();
+
+        assert_eq!(error(""), "Missing 'Time:' line");
+        assert_eq!(error("Time: 7"), "Missing 'Distance:' line");
+        assert_eq!(error("Distance: 9\nTime: 7"), "Expected a line starting with 'Time:', found 'Distance: 9'");
+        assert_eq!(error("Time: 7 15\nDistance: 9"), "Found 2 times but 1 distances");
+        assert_eq!(error("Time: 7\nDistance: x"), "Failed to parse distance 'x': invalid digit found in string");
+        assert_eq!(error("Time: 7 -1\nDistance: 9 9"), "Failed to parse time '-1': invalid digit found in string");
+    }
+
     #[test]
     fn test_get_min_winning_press() {
         let race = Race {
             total_time: 7,
             record_distance: 9,
         };
-        assert_eq!(race.get_min_winning_press(), 2);
+        assert_eq!(race.get_min_winning_press(), Some(2));
 
         let race = Race {
             total_time: 15,
             record_distance: 40,
         };
-        assert_eq!(race.get_min_winning_press(), 4);
+        assert_eq!(race.get_min_winning_press(), Some(4));
 
         let race = Race {
             total_time: 30,
             record_distance: 200,
         };
-        assert_eq!(race.get_min_winning_press(), 11);
+        assert_eq!(race.get_min_winning_press(), Some(11));
     }
 
     #[test]
@@ -142,19 +257,19 @@ Distance:  9  40  200";
             total_time: 7,
             record_distance: 9,
         };
-        assert_eq!(race.get_max_winning_press(), 5);
+        assert_eq!(race.get_max_winning_press(), Some(5));
 
         let race = Race {
             total_time: 15,
             record_distance: 40,
         };
-        assert_eq!(race.get_max_winning_press(), 11);
+        assert_eq!(race.get_max_winning_press(), Some(11));
 
         let race = Race {
             total_time: 30,
             record_distance: 200,
         };
-        assert_eq!(race.get_max_winning_press(), 19);
+        assert_eq!(race.get_max_winning_press(), Some(19));
     }
 
     #[test]
@@ -163,18 +278,77 @@ Distance:  9  40  200";
             total_time: 7,
             record_distance: 9,
         };
-        assert_eq!(race.get_num_winning_presses(), 4);
+        assert_eq!(ways_to_win(&race), 4);
 
         let race = Race {
             total_time: 15,
             record_distance: 40,
         };
-        assert_eq!(race.get_num_winning_presses(), 8);
+        assert_eq!(ways_to_win(&race), 8);
 
         let race = Race {
             total_time: 30,
             record_distance: 200,
         };
-        assert_eq!(race.get_num_winning_presses(), 9);
+        assert_eq!(ways_to_win(&race), 9);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_no_winning_press() {
+        // The best possible is 3 x 3 = 9, which only ties
+        let race = Race {
+            total_time: 6,
+            record_distance: 9,
+        };
+        assert_eq!(race.get_min_winning_press(), None);
+        assert_eq!(race.get_max_winning_press(), None);
+        assert_eq!(ways_to_win(&race), 0);
+
+        let race = Race {
+            total_time: 0,
+            record_distance: 0,
+        };
+        assert_eq!(ways_to_win(&race), 0);
+    }
+
+    #[test]
+    fn test_large_race() {
+        // Far beyond f64's 53 bits; pressing 12345 exactly ties the record
+        let total_time = 1 << 100;
+        let race = Race {
+            total_time,
+            record_distance: 12345 * (total_time - 12345),
+        };
+        assert_eq!(race.get_min_winning_press(), Some(12346));
+        assert_eq!(race.get_max_winning_press(), Some(total_time - 12346));
+
+        // Distances near the middle overflow u128 but still win
+        let race = Race {
+            total_time: u128::MAX,
+            record_distance: u128::MAX,
+        };
+        assert_eq!(race.get_min_winning_press(), Some(2));
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        // Seeded, so the races are the same on every run
+        let mut rng = StdRng::seed_from_u64(2023);
+
+        for _ in 0..2000 {
+            let total_time = rng.gen_range(0..200);
+            let race = Race {
+                total_time,
+                record_distance: rng.gen_range(0..total_time * total_time / 4 + 2),
+            };
+
+            let winning: Vec<u128> = (0..=total_time)
+                .filter(|press| press * (total_time - press) > race.record_distance)
+                .collect();
+
+            assert_eq!(race.get_min_winning_press(), winning.first().copied(), "{:?}", race);
+            assert_eq!(race.get_max_winning_press(), winning.last().copied(), "{:?}", race);
+            assert_eq!(ways_to_win(&race), winning.len() as u128, "{:?}", race);
+        }
+    }
+}
@@ -0,0 +1,319 @@
+use std::fmt;
+
+use crate::Race;
+
+// Searches stop after this many steps even if floats can't get closer
+const MAX_ITERATIONS: usize = 200;
+
+// Beyond this, whole hold times aren't all representable as f64, so only
+// models with an integer solver can report on the race
+const EXACT_LIMIT: u128 = 1 << 53;
+
+// How far a boat gets when the button is held for `hold` out of `total_time`
+pub trait BoatModel {
+    fn distance(&self, hold: f64, total_time: f64) -> f64;
+
+    // A report worked out with integers, for models that have a closed form
+    fn exact_report(&self, _race: &Race) -> Option<RaceReport> {
+        None
+    }
+}
+
+// The puzzle's boat: speed equals hold time, then constant speed
+pub struct Linear;
+
+// Charging gets faster the longer it is held: speed is rate × hold²
+pub struct Quadratic {
+    pub rate: f64,
+}
+
+// Charging stops helping past a top speed
+pub struct Capped {
+    pub max_speed: f64,
+}
+
+// Water slows the boat: speed decays by `coefficient` per unit of time
+pub struct Drag {
+    pub coefficient: f64,
+}
+
+impl BoatModel for Linear {
+    fn distance(&self, hold: f64, total_time: f64) -> f64 {
+        hold * (total_time - hold)
+    }
+
+    // The race's own solver, exact for any u128 time
+    fn exact_report(&self, race: &Race) -> Option<RaceReport> {
+        let optimal_hold = race.total_time / 2;
+        Some(RaceReport {
+            total_time: race.total_time,
+            record_distance: race.record_distance,
+            optimal_hold,
+            best_distance: optimal_hold as f64 * (race.total_time - optimal_hold) as f64,
+            winning: race.get_min_winning_press().zip(race.get_max_winning_press()),
+        })
+    }
+}
+
+impl BoatModel for Quadratic {
+    fn distance(&self, hold: f64, total_time: f64) -> f64 {
+        self.rate * hold * hold * (total_time - hold)
+    }
+}
+
+impl BoatModel for Capped {
+    fn distance(&self, hold: f64, total_time: f64) -> f64 {
+        hold.min(self.max_speed) * (total_time - hold)
+    }
+}
+
+impl BoatModel for Drag {
+    fn distance(&self, hold: f64, total_time: f64) -> f64 {
+        // Integral of hold × e^(-kt) over the time left
+        hold * (1.0 - (-self.coefficient * (total_time - hold)).exp()) / self.coefficient
+    }
+}
+
+// Parse "linear", "quadratic:0.5", "capped:20" or "drag:0.1"
+pub fn parse_model(model: &str) -> Result<Box<dyn BoatModel>, String> {
+    let (name, parameter) = match model.split_once(':') {
+        Some((name, parameter)) => {
+            let value = parameter.parse::<f64>()
+                .map_err(|e| format!("Failed to parse model parameter '{}': {}", parameter, e))?;
+            if value <= 0.0 {
+                return Err(format!("Model parameter must be positive, found {}", value));
+            }
+            (name, Some(value))
+        },
+        None => (model, None),
+    };
+
+    match (name, parameter) {
+        ("linear", None) => Ok(Box::new(Linear)),
+        ("quadratic", Some(rate)) => Ok(Box::new(Quadratic { rate })),
+        ("capped", Some(max_speed)) => Ok(Box::new(Capped { max_speed })),
+        ("drag", Some(coefficient)) => Ok(Box::new(Drag { coefficient })),
+        ("linear", Some(_)) => Err("The linear model takes no parameter".to_string()),
+        ("quadratic" | "capped" | "drag", None) => Err(format!("The {} model needs a parameter, like '{}:2'", name, name)),
+        _ => Err(format!("Unknown boat model '{}'", name)),
+    }
+}
+
+// Point of `low..=high` where a function rising then falling is highest,
+// by golden section search
+pub fn find_peak<F: Fn(f64) -> f64>(f: F, mut low: f64, mut high: f64, tolerance: f64) -> f64 {
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+
+    for _ in 0..MAX_ITERATIONS {
+        if high - low <= tolerance {
+            break;
+        }
+        let left = high - ratio * (high - low);
+        let right = low + ratio * (high - low);
+        if f(left) < f(right) {
+            low = left;
+        } else {
+            high = right;
+        }
+    }
+    (low + high) / 2.0
+}
+
+// Where `f` changes sign between `low` and `high`, by bisection. Returns
+// None if it has the same sign at both ends.
+pub fn find_root<F: Fn(f64) -> f64>(f: F, mut low: f64, mut high: f64, tolerance: f64) -> Option<f64> {
+    let low_positive = f(low) > 0.0;
+    if low_positive == (f(high) > 0.0) {
+        return None;
+    }
+
+    for _ in 0..MAX_ITERATIONS {
+        if high - low <= tolerance {
+            break;
+        }
+        let middle = (low + high) / 2.0;
+        if (f(middle) > 0.0) == low_positive {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    Some((low + high) / 2.0)
+}
+
+#[derive(PartialEq, Debug)]
+pub struct RaceReport {
+    pub total_time: u128,
+    pub record_distance: u128,
+    // Whole hold time going furthest, the earliest if several tie
+    pub optimal_hold: u128,
+    pub best_distance: f64,
+    // Winning hold times, first and last
+    pub winning: Option<(u128, u128)>,
+}
+
+impl RaceReport {
+    pub fn margin(&self) -> f64 {
+        self.best_distance - self.record_distance as f64
+    }
+
+    pub fn ways_to_win(&self) -> u128 {
+        self.winning.map_or(0, |(first, last)| last - first + 1)
+    }
+}
+
+impl fmt::Display for RaceReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f, "Time {}, record {}: hold {} to go {:.2} ({:+.2} over the record), ",
+            self.total_time, self.record_distance, self.optimal_hold, self.best_distance, self.margin()
+        )?;
+        match self.winning {
+            Some((first, last)) => write!(f, "wins holding {} to {} ({} ways)", first, last, self.ways_to_win()),
+            None => write!(f, "cannot win"),
+        }
+    }
+}
+
+impl Race {
+    // Use the model's integer solver if it has one, and search with floats
+    // otherwise while every hold time is still exact as an f64
+    pub fn report(&self, model: &dyn BoatModel) -> Result<RaceReport, String> {
+        if let Some(report) = model.exact_report(self) {
+            return Ok(report);
+        }
+        if self.total_time >= EXACT_LIMIT {
+            return Err(format!("Time {} is too long to solve this model with floats", self.total_time));
+        }
+        Ok(self.search(model))
+    }
+
+    fn search(&self, model: &dyn BoatModel) -> RaceReport {
+        let total_time = self.total_time as f64;
+        let record = self.record_distance as f64;
+        let distance = |hold: u128| model.distance(hold as f64, total_time);
+        let wins = |hold: u128| hold <= self.total_time && distance(hold) > record;
+
+        let peak = find_peak(|hold| model.distance(hold, total_time), 0.0, total_time, 1e-6);
+        let optimal_hold = [peak.floor() as u128, (peak.ceil() as u128).min(self.total_time)]
+            .into_iter()
+            .fold(0, |best, hold| if distance(hold) > distance(best) { hold } else { best });
+        let best_distance = distance(optimal_hold);
+
+        if !wins(optimal_hold) {
+            return RaceReport { total_time: self.total_time, record_distance: self.record_distance, optimal_hold, best_distance, winning: None };
+        }
+
+        // Roots land near the whole hold times at each end of the winning
+        // interval, so step from there to the exact ones
+        let beyond_record = |hold: f64| model.distance(hold, total_time) - record;
+        let rise = find_root(beyond_record, 0.0, optimal_hold as f64, 1e-6).unwrap_or(0.0);
+        let fall = find_root(beyond_record, optimal_hold as f64, total_time, 1e-6).unwrap_or(total_time);
+
+        let mut first = (rise.ceil() as u128).min(optimal_hold);
+        let mut last = (fall.floor() as u128).max(optimal_hold);
+        while first > 0 && wins(first - 1) {
+            first -= 1;
+        }
+        while !wins(first) {
+            first += 1;
+        }
+        while wins(last + 1) {
+            last += 1;
+        }
+        while !wins(last) {
+            last -= 1;
+        }
+
+        RaceReport {
+            total_time: self.total_time,
+            record_distance: self.record_distance,
+            optimal_hold,
+            best_distance,
+            winning: Some((first, last)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn race(total_time: u128, record_distance: u128) -> Race {
+        Race { total_time, record_distance }
+    }
+
+    #[test]
+    fn test_linear_matches_exact_solver() {
+        for total_time in 0..60 {
+            for record_distance in (0..total_time * total_time / 4 + 2).step_by(3) {
+                let race = race(total_time, record_distance);
+                let report = race.search(&Linear);
+                let exact = race.get_min_winning_press().zip(race.get_max_winning_press());
+
+                assert_eq!(report.winning, exact, "{:?}", race);
+                assert_eq!(race.report(&Linear), Ok(report));
+            }
+        }
+    }
+
+    #[test]
+    fn test_report() {
+        let report = race(7, 9).report(&Linear).unwrap();
+
+        assert_eq!(report.optimal_hold, 3);
+        assert_eq!(report.best_distance, 12.0);
+        assert_eq!(report.margin(), 3.0);
+        assert_eq!(report.to_string(), "Time 7, record 9: hold 3 to go 12.00 (+3.00 over the record), wins holding 2 to 5 (4 ways)");
+
+        assert_eq!(race(6, 9).report(&Linear).unwrap().to_string(), "Time 6, record 9: hold 3 to go 9.00 (+0.00 over the record), cannot win");
+    }
+
+    #[test]
+    fn test_other_models() {
+        // 2h² × (10 - h) is best at h = 6.67; holding 7 goes 294
+        let report = race(10, 200).report(&Quadratic { rate: 2.0 }).unwrap();
+        assert_eq!(report.optimal_hold, 7);
+        assert_eq!(report.best_distance, 294.0);
+        let brute: Vec<u128> = (0..=10).filter(|&hold| 2 * hold * hold * (10 - hold) > 200).collect();
+        assert_eq!(report.winning, Some((brute[0], *brute.last().unwrap())));
+
+        // Past speed 3, holding longer only loses time
+        let report = race(30, 50).report(&Capped { max_speed: 3.0 }).unwrap();
+        assert_eq!(report.optimal_hold, 3);
+        assert_eq!(report.winning, Some((2, 13)));
+
+        // Drag always costs distance compared with no drag
+        let report = race(30, 200).report(&Drag { coefficient: 0.05 }).unwrap();
+        assert!(report.best_distance < 225.0);
+        assert!(report.ways_to_win() < race(30, 200).report(&Linear).unwrap().ways_to_win());
+    }
+
+    #[test]
+    fn test_parse_model() {
+        assert!(parse_model("linear").is_ok());
+        assert_eq!(parse_model("quadratic:0.5").unwrap().distance(2.0, 4.0), 4.0);
+        assert_eq!(parse_model("capped:1").unwrap().distance(2.0, 4.0), 2.0);
+        assert!(parse_model("linear:2").is_err());
+        assert!(parse_model("drag").is_err());
+        assert!(parse_model("drag:-1").is_err());
+        assert!(parse_model("sail:2").is_err());
+    }
+
+    #[test]
+    fn test_find_root() {
+        let root = find_root(|x| x * x - 2.0, 0.0, 2.0, 1e-9).unwrap();
+        assert!((root - 2f64.sqrt()).abs() < 1e-8);
+        assert_eq!(find_root(|x| x * x + 1.0, 0.0, 2.0, 1e-9), None);
+
+        // Too big for the tolerance to be reached, but still finishes
+        let huge = 2f64.powi(100);
+        assert!(find_root(|x| x - huge / 3.0, 0.0, huge, 1e-6).is_some());
+        assert!((find_peak(|x| -(x - huge / 3.0).abs(), 0.0, huge, 1e-6) / huge - 1.0 / 3.0).abs() < 1e-9);
+
+        // Past 2^53 only the linear model's integer solver is exact
+        let report = race(1 << 100, 1 << 60).report(&Linear).unwrap();
+        assert_eq!(report.winning, race(1 << 100, 1 << 60).get_min_winning_press().zip(race(1 << 100, 1 << 60).get_max_winning_press()));
+        assert!(race(1 << 100, 1 << 60).report(&Quadratic { rate: 1.0 }).is_err());
+    }
+}
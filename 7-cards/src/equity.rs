@@ -0,0 +1,238 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::{Card, Hand, HandType, Rank, RuleSet};
+
+// Above this many ways to fill the unknown cards, sample instead
+const EXHAUSTIVE_LIMIT: usize = 371_293;
+
+// A hand with some cards not dealt yet, like "KK?J?". Every unknown card is
+// equally likely to be any of the 13 ranks.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct PartialHand {
+    cards: Vec<Option<Rank>>,
+}
+
+impl TryFrom<&str> for PartialHand {
+    type Error = &'static str;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let cards = value.chars()
+            .map(|c| if c == '?' { Ok(None) } else { Rank::try_from(c).map(Some) })
+            .collect::<Result<Vec<_>, _>>()?;
+        if cards.is_empty() {
+            return Err("Hand has no cards");
+        }
+
+        Ok(PartialHand { cards })
+    }
+}
+
+impl PartialHand {
+    fn unknowns(&self) -> usize {
+        self.cards.iter().filter(|card| card.is_none()).count()
+    }
+
+    // Fill the unknown cards from `choices`, one rank index in 0..13 each
+    fn fill(&self, choices: &mut impl Iterator<Item = usize>, rules: &RuleSet) -> Hand {
+        let cards = self.cards.iter()
+            .map(|card| {
+                let rank = card.unwrap_or_else(|| Rank::from(2 + choices.next().expect("A choice for every unknown card")));
+                Card { rank }
+            })
+            .collect();
+
+        Hand { cards, bid: 0, rules: rules.clone() }
+    }
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Method {
+    Exhaustive,
+    MonteCarlo,
+}
+
+// Visit the hands every way of filling the unknowns, or `trials` random ways
+// if there are too many
+fn deal<F: FnMut(&[Hand])>(hands: &[&PartialHand], rules: &RuleSet, trials: usize, seed: u64, mut visit: F) -> (Method, usize) {
+    let unknowns: usize = hands.iter().map(|hand| hand.unknowns()).sum();
+    let outcomes = 13usize.checked_pow(unknowns as u32).filter(|&outcomes| outcomes <= EXHAUSTIVE_LIMIT);
+
+    let mut fill = |mut choices: &mut dyn Iterator<Item = usize>| {
+        let dealt: Vec<Hand> = hands.iter().map(|hand| hand.fill(&mut choices, rules)).collect();
+        visit(&dealt);
+    };
+
+    match outcomes {
+        Some(outcomes) => {
+            for outcome in 0..outcomes {
+                fill(&mut (0..unknowns).map(|i| outcome / 13usize.pow(i as u32) % 13));
+            }
+            (Method::Exhaustive, outcomes)
+        },
+        None => {
+            // With no samples every probability would be 0 / 0
+            assert!(trials > 0, "Need at least one trial to sample hands");
+            let mut rng = StdRng::seed_from_u64(seed);
+            for _ in 0..trials {
+                let choices: Vec<usize> = (0..unknowns).map(|_| rng.gen_range(0..13)).collect();
+                fill(&mut choices.into_iter());
+            }
+            (Method::MonteCarlo, trials)
+        },
+    }
+}
+
+// How likely a partial hand is to end up as each type
+#[derive(Debug)]
+pub struct TypeOdds {
+    pub method: Method,
+    pub samples: usize,
+    // Keyed by `HandType::hand`, so each type appears once
+    pub probabilities: BTreeMap<HandType, f64>,
+}
+
+pub fn type_odds(hand: &PartialHand, rules: &RuleSet, trials: usize, seed: u64) -> TypeOdds {
+    let mut counts: BTreeMap<HandType, usize> = BTreeMap::new();
+    let (method, samples) = deal(&[hand], rules, trials, seed, |dealt| {
+        *counts.entry(dealt[0].hand_type().hand()).or_default() += 1;
+    });
+
+    let probabilities = counts.into_iter()
+        .map(|(hand_type, count)| (hand_type, count as f64 / samples as f64))
+        .collect();
+    TypeOdds { method, samples, probabilities }
+}
+
+// How often one partial hand beats another
+#[derive(Debug, PartialEq)]
+pub struct Matchup {
+    pub method: Method,
+    pub samples: usize,
+    pub win: f64,
+    pub tie: f64,
+    pub loss: f64,
+}
+
+pub fn matchup(hand: &PartialHand, other: &PartialHand, rules: &RuleSet, trials: usize, seed: u64) -> Matchup {
+    let mut counts = [0; 3];
+    let (method, samples) = deal(&[hand, other], rules, trials, seed, |dealt| {
+        let outcome = match dealt[0].cmp(&dealt[1]) {
+            Ordering::Greater => 0,
+            Ordering::Equal => 1,
+            Ordering::Less => 2,
+        };
+        counts[outcome] += 1;
+    });
+
+    let share = |count: usize| count as f64 / samples as f64;
+    Matchup { method, samples, win: share(counts[0]), tie: share(counts[1]), loss: share(counts[2]) }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Method::Exhaustive => write!(f, "exhaustive"),
+            Method::MonteCarlo => write!(f, "Monte Carlo"),
+        }
+    }
+}
+
+impl fmt::Display for TypeOdds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Hand types ({}, {} hands):", self.method, self.samples)?;
+        for (hand_type, probability) in self.probabilities.iter().rev() {
+            writeln!(f, "  {:<14} {:>7.3}%", hand_type.name(), probability * 100.0)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Matchup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f, "Win {:.3}%, tie {:.3}%, loss {:.3}% ({}, {} deals)",
+            self.win * 100.0, self.tie * 100.0, self.loss * 100.0, self.method, self.samples
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn partial(cards: &str) -> PartialHand {
+        PartialHand::try_from(cards).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(partial("K?").cards, vec![Some(Rank::King), None]);
+        assert_eq!(partial("KK?J?").unknowns(), 2);
+        assert_eq!(PartialHand::try_from("KX"), Err("Invalid card"));
+        assert_eq!(PartialHand::try_from(""), Err("Hand has no cards"));
+    }
+
+    #[test]
+    fn test_type_odds() {
+        // KKKK plus one unknown: 1 in 13 is another K, with jokers 2 in 13
+        let odds = type_odds(&partial("KKKK?"), &RuleSet::Standard, 0, 1);
+        assert_eq!(odds.method, Method::Exhaustive);
+        assert_eq!(odds.samples, 13);
+        assert_eq!(odds.probabilities[&HandType::FiveOfAKind(Rank::Ace)], 1.0 / 13.0);
+        assert_eq!(odds.probabilities[&HandType::FourOfAKind(Rank::Ace)], 12.0 / 13.0);
+
+        let odds = type_odds(&partial("KKKK?"), &RuleSet::Jokers, 0, 1);
+        assert_eq!(odds.probabilities[&HandType::FiveOfAKind(Rank::Ace)], 2.0 / 13.0);
+
+        // A known hand has one outcome
+        let odds = type_odds(&partial("QQQJA"), &RuleSet::Jokers, 0, 1);
+        assert_eq!(odds.probabilities.len(), 1);
+        assert_eq!(odds.probabilities[&HandType::FourOfAKind(Rank::Ace)], 1.0);
+    }
+
+    #[test]
+    fn test_probabilities_sum_to_one() {
+        let odds = type_odds(&partial("?????"), &RuleSet::Jokers, 0, 1);
+        assert_eq!(odds.samples, 13usize.pow(5));
+        assert!((odds.probabilities.values().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(odds.to_string().starts_with("Hand types (exhaustive, 371293 hands):\n  FiveOfAKind"));
+    }
+
+    #[test]
+    fn test_matchup() {
+        let result = matchup(&partial("AAAAA"), &partial("KKKK?"), &RuleSet::Standard, 0, 1);
+        assert_eq!(result, Matchup { method: Method::Exhaustive, samples: 13, win: 1.0, tie: 0.0, loss: 0.0 });
+
+        // Identical hands with one unknown each tie 13 times out of 169
+        let result = matchup(&partial("2345?"), &partial("2345?"), &RuleSet::Standard, 0, 1);
+        assert_eq!(result.tie, 13.0 / 169.0);
+        assert!((result.win - result.loss).abs() < 1e-12);
+    }
+
+    #[test]
+    #[should_panic(expected = "Need at least one trial")]
+    fn test_no_trials() {
+        matchup(&partial("???"), &partial("???"), &RuleSet::Standard, 0, 7);
+    }
+
+    #[test]
+    fn test_monte_carlo() {
+        // Six unknowns is too many to enumerate
+        let sampled = matchup(&partial("???"), &partial("???"), &RuleSet::Standard, 50_000, 7);
+        assert_eq!(sampled.method, Method::MonteCarlo);
+        assert_eq!(sampled.samples, 50_000);
+        assert!((sampled.win + sampled.tie + sampled.loss - 1.0).abs() < 1e-9);
+
+        // Both sides are dealt alike, and only identical hands tie
+        assert!((sampled.win - sampled.loss).abs() < 0.02);
+        assert!(sampled.tie < 0.002);
+
+        // The same seed deals the same hands
+        assert_eq!(sampled, matchup(&partial("???"), &partial("???"), &RuleSet::Standard, 50_000, 7));
+    }
+}
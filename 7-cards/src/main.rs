@@ -1,3 +1,7 @@
+mod equity;
+mod poker;
+mod standings;
+
 use std::{
     env,
     fmt::Error,
@@ -5,21 +9,23 @@ use std::{
     io::{BufRead, BufReader},
 };
 
+use standings::{SortKey, Standings};
+
 #[derive(Copy, Clone, Eq, PartialEq, Debug, PartialOrd, Ord)]
 enum Rank {
     Ace = 14,
     King = 13,
     Queen = 12,
-    Ten = 11,
-    Nine = 10,
-    Eight = 9,
-    Seven = 8,
-    Six = 7,
-    Five = 6,
-    Four = 5,
-    Three = 4,
-    Two = 3,
-    Jack = 2,
+    Jack = 11,
+    Ten = 10,
+    Nine = 9,
+    Eight = 8,
+    Seven = 7,
+    Six = 6,
+    Five = 5,
+    Four = 4,
+    Three = 3,
+    Two = 2,
 }
 
 impl From<usize> for Rank {
@@ -28,27 +34,94 @@ impl From<usize> for Rank {
             14 => Rank::Ace,
             13 => Rank::King,
             12 => Rank::Queen,
-            11 => Rank::Ten,
-            10 => Rank::Nine,
-            9 => Rank::Eight,
-            8 => Rank::Seven,
-            7 => Rank::Six,
-            6 => Rank::Five,
-            5 => Rank::Four,
-            4 => Rank::Three,
-            3 => Rank::Two,
-            2 => Rank::Jack,
+            11 => Rank::Jack,
+            10 => Rank::Ten,
+            9 => Rank::Nine,
+            8 => Rank::Eight,
+            7 => Rank::Seven,
+            6 => Rank::Six,
+            5 => Rank::Five,
+            4 => Rank::Four,
+            3 => Rank::Three,
+            2 => Rank::Two,
             _ => panic!("Invalid rank"),
         }
     }
 }
 
+impl TryFrom<char> for Rank {
+    type Error = &'static str;
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        match value {
+            'A' => Ok(Rank::Ace),
+            'K' => Ok(Rank::King),
+            'Q' => Ok(Rank::Queen),
+            'J' => Ok(Rank::Jack),
+            'T' => Ok(Rank::Ten),
+            '9' => Ok(Rank::Nine),
+            '8' => Ok(Rank::Eight),
+            '7' => Ok(Rank::Seven),
+            '6' => Ok(Rank::Six),
+            '5' => Ok(Rank::Five),
+            '4' => Ok(Rank::Four),
+            '3' => Ok(Rank::Three),
+            '2' => Ok(Rank::Two),
+            _ => Err("Invalid card"),
+        }
+    }
+}
+
+// Which cards are wild and how cards compare when hand types tie. The
+// order only groups hands played under different rules.
+#[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Debug)]
+enum RuleSet {
+    // No wildcards, J ranks between T and Q
+    Standard,
+    // J is a wildcard and ranks below 2
+    Jokers,
+    // Every card of these ranks is a wildcard and ranks below 2
+    Wildcards(Vec<Rank>),
+}
+
+impl RuleSet {
+    fn is_wildcard(&self, rank: Rank) -> bool {
+        match self {
+            RuleSet::Standard => false,
+            RuleSet::Jokers => rank == Rank::Jack,
+            RuleSet::Wildcards(ranks) => ranks.contains(&rank),
+        }
+    }
+
+    // Value used to compare cards one by one
+    fn card_value(&self, rank: Rank) -> u8 {
+        if self.is_wildcard(rank) {
+            1
+        } else {
+            rank as u8
+        }
+    }
+}
+
+impl std::str::FromStr for RuleSet {
+    type Err = &'static str;
+
+    // "standard", "jokers", or the wildcard ranks like "JQ"
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "standard" => Ok(RuleSet::Standard),
+            "jokers" => Ok(RuleSet::Jokers),
+            _ => Ok(RuleSet::Wildcards(value.chars().map(Rank::try_from).collect::<Result<_, _>>()?)),
+        }
+    }
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, PartialOrd, Ord)]
 struct Card {
     rank: Rank,
 }
 
-#[derive(Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord)]
 #[repr(u8)]
 enum HandType {
     FiveOfAKind(Rank) = 6,
@@ -62,11 +135,24 @@ enum HandType {
 
 #[derive(Debug, Eq, PartialEq)]
 struct Hand {
-    cards: [Card; 5],
+    cards: Vec<Card>,
     bid: u32,
+    rules: RuleSet,
 }
 
 impl HandType {
+    fn name(&self) -> &'static str {
+        match self {
+            HandType::FiveOfAKind(_) => "FiveOfAKind",
+            HandType::FourOfAKind(_) => "FourOfAKind",
+            HandType::FullHouse(_, _) => "FullHouse",
+            HandType::ThreeOfAKind(_) => "ThreeOfAKind",
+            HandType::TwoPair(_, _) => "TwoPair",
+            HandType::OnePair(_) => "OnePair",
+            HandType::HighCard(_) => "HighCard",
+        }
+    }
+
     fn hand(&self) -> HandType {
         match self {
             HandType::FiveOfAKind(_) => HandType::FiveOfAKind(Rank::Ace),
@@ -80,12 +166,34 @@ impl HandType {
     }
 }
 
+impl TryFrom<&str> for HandType {
+    type Error = String;
+
+    // A type by its name like "FullHouse", as returned by `hand`
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let types = [
+            HandType::FiveOfAKind(Rank::Ace),
+            HandType::FourOfAKind(Rank::Ace),
+            HandType::FullHouse(Rank::Ace, Rank::Ace),
+            HandType::ThreeOfAKind(Rank::Ace),
+            HandType::TwoPair(Rank::Ace, Rank::Ace),
+            HandType::OnePair(Rank::Ace),
+            HandType::HighCard(Rank::Ace),
+        ];
+        types.into_iter().find(|hand_type| hand_type.name() == value).ok_or_else(|| {
+            let names: Vec<&str> = types.iter().map(|hand_type| hand_type.name()).collect();
+            format!("Unknown hand type '{}', expected {}", value, names.join(", "))
+        })
+    }
+}
+
 impl std::fmt::Debug for Card {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), Error> {
         let rank_str = match self.rank {
             Rank::Ace => "A",
             Rank::King => "K",
             Rank::Queen => "Q",
+            Rank::Jack => "J",
             Rank::Ten => "T",
             Rank::Nine => "9",
             Rank::Eight => "8",
@@ -95,7 +203,6 @@ impl std::fmt::Debug for Card {
             Rank::Four => "4",
             Rank::Three => "3",
             Rank::Two => "2",
-            Rank::Jack => "J",
         };
 
         write!(f, "{}", rank_str)
@@ -104,222 +211,108 @@ impl std::fmt::Debug for Card {
 
 impl PartialOrd for Hand {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        let self_hand_type = self.hand_type();
-        let other_hand_type = other.hand_type();
-
-        if self_hand_type.hand() == other_hand_type.hand() {
-            Some(self.cards.cmp(&other.cards))
-        } else {
-            Some(self_hand_type.cmp(&other_hand_type))
-        }
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for Hand {
+    // Ranked by the rules both hands were parsed with. Hands parsed with
+    // different rules have no ranking between them, so they are ordered by
+    // their rules first; use cmp_with to rank them under one set of rules.
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        let self_hand_type = self.hand_type();
-        let other_hand_type = other.hand_type();
-
-        if self_hand_type.hand() == other_hand_type.hand() {
-            self.cards.cmp(&other.cards)
-        } else {
-            self_hand_type.cmp(&other_hand_type)
-        }
+        self.rules.cmp(&other.rules).then_with(|| self.cmp_with(other, &self.rules))
     }
 }
 
 impl TryFrom<String> for Hand {
-    // Given a single-line string like "32T3K 765", parse it into a Hand.
-    // The first five characters are the cards, and the rest is the bid.
+    // Given a single-line string like "32T3K 765", parse it into a Hand
+    // played with jokers.
 
     type Error = &'static str;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        let mut cards = [Card { rank: Rank::Ace }; 5];
-        let mut i = 0;
-        for c in value.chars() {
-            if i >= 5 {
-                break;
-            }
-            match c {
-                'A' => cards[i].rank = Rank::Ace,
-                'K' => cards[i].rank = Rank::King,
-                'Q' => cards[i].rank = Rank::Queen,
-                'T' => cards[i].rank = Rank::Ten,
-                '9' => cards[i].rank = Rank::Nine,
-                '8' => cards[i].rank = Rank::Eight,
-                '7' => cards[i].rank = Rank::Seven,
-                '6' => cards[i].rank = Rank::Six,
-                '5' => cards[i].rank = Rank::Five,
-                '4' => cards[i].rank = Rank::Four,
-                '3' => cards[i].rank = Rank::Three,
-                '2' => cards[i].rank = Rank::Two,
-                'J' => cards[i].rank = Rank::Jack,
-                _ => return Err("Invalid card"),
-            }
-            i += 1;
-        }
-
-        let bid_str = &value[6..];
-
-        let bid = bid_str.parse().or(Err("Invalid bid"))?;
-
-        Ok(Hand { cards, bid })
+        Hand::parse(&value, RuleSet::Jokers)
     }
 }
 
 impl Hand {
-    fn hand_type(&self) -> HandType {
-        
-        let mut ranks = [0; 15];
+    // The cards, then whitespace, then the bid
+    fn parse(value: &str, rules: RuleSet) -> Result<Self, &'static str> {
+        let mut fields = value.split_whitespace();
+        let (cards_str, bid_str) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(cards_str), Some(bid_str), None) => (cards_str, bid_str),
+            (None, _, _) => return Err("Hand has no cards"),
+            _ => return Err("Invalid bid"),
+        };
 
-        for card in self.cards.iter() {
-            ranks[card.rank as usize] += 1;
-        }
+        let cards = cards_str.chars()
+            .map(|c| Ok(Card { rank: Rank::try_from(c)? }))
+            .collect::<Result<Vec<Card>, &'static str>>()?;
 
-        let mut num_pairs = 0;
-        let mut num_triples = 0;
-        let mut num_quads = 0;
-        let mut num_quints = 0;
-        let mut high_card_rank: Option<Rank> = None;
-        let mut first_pair_rank: Option<Rank> = None;
-        let mut second_pair_rank: Option<Rank> = None;
-        let mut triple_rank: Option<Rank> = None;
-        let mut quad_rank: Option<Rank> = None;
-        let mut quint_rank: Option<Rank> = None;
-
-        for (rank, count) in (2..15).zip(ranks[2..15].iter()).rev() {
-            match count {
-                1 => {
-                    if high_card_rank.is_none() {
-                        high_card_rank = Some(Rank::from(rank));
-                    }
-                }
-                2 => {
-                    num_pairs += 1;
-                    if first_pair_rank.is_none() {
-                        first_pair_rank = Some(Rank::from(rank));
-                    } else {
-                        second_pair_rank = Some(Rank::from(rank));
-                    }
-                }
-                3 => {
-                    num_triples += 1;
-                    triple_rank = Some(Rank::from(rank));
-                }
-                4 => {
-                    num_quads += 1;
-                    quad_rank = Some(Rank::from(rank));
-                }
-                5 => {
-                    num_quints += 1;
-                    quint_rank = Some(Rank::from(rank));
-                }
-                _ => (),
-            }
-        }
+        let bid = bid_str.parse().or(Err("Invalid bid"))?;
 
-        let hand_type = match num_quints {
-            1 => HandType::FiveOfAKind(quint_rank.expect("No quint rank")),
-            0 => match num_quads {
-                1 => HandType::FourOfAKind(quad_rank.expect("No quad rank")),
-                0 => match num_triples {
-                    1 => match num_pairs {
-                        1 => HandType::FullHouse(
-                            triple_rank.expect("No triple rank"),
-                            first_pair_rank.expect("No first pair rank"),
-                        ),
-                        0 => HandType::ThreeOfAKind(triple_rank.expect("No triple rank")),
-                        _ => panic!("Invalid hand"),
-                    },
-                    0 => match num_pairs {
-                        2 => HandType::TwoPair(
-                            first_pair_rank.expect("No first pair rank"),
-                            second_pair_rank.expect("No second pair rank"),
-                        ),
-                        1 => HandType::OnePair(first_pair_rank.expect("No first pair rank")),
-                        0 => match high_card_rank {
-                            Some(rank) => HandType::HighCard(rank),
-                            None => panic!("Invalid hand, no high card: {:?}", self),
-                        },
-                        _ => panic!("Invalid hand"),
-                    },
-                    _ => panic!("Invalid hand"),
-                },
-                _ => panic!("Invalid hand"),
-            },
-            _ => panic!("Invalid hand"),
-        };
+        Ok(Hand { cards, bid, rules })
+    }
 
-        let joker_count = self.cards.iter().filter(|c| c.rank == Rank::Jack).count();
+    // Compare as if both hands were played under `rules`
+    fn cmp_with(&self, other: &Self, rules: &RuleSet) -> std::cmp::Ordering {
+        let self_hand_type = self.hand_type_with(rules);
+        let other_hand_type = other.hand_type_with(rules);
 
-        //Get highest card that is not a Jack
-        let mut highest_card = Rank::Two;
-        for card in self.cards.iter() {
-            if card.rank != Rank::Jack && card.rank > highest_card {
-                highest_card = Rank::from(card.rank);
-                break;
-            }
+        if self_hand_type.hand() == other_hand_type.hand() {
+            let values = |hand: &Hand| hand.cards.iter().map(|card| rules.card_value(card.rank)).collect::<Vec<u8>>();
+            values(self).cmp(&values(other))
+        } else {
+            self_hand_type.cmp(&other_hand_type)
         }
+    }
+
+    fn hand_type(&self) -> HandType {
+        self.hand_type_with(&self.rules)
+    }
 
-        if joker_count == 0 {
-            return hand_type;
+    fn hand_type_with(&self, rules: &RuleSet) -> HandType {
+        let ranks: Vec<Rank> = self.cards.iter().map(|card| card.rank).collect();
+        classify(&ranks, rules)
+    }
+}
+
+// Classify any number of cards by how many there are of each rank. Wildcards
+// always do best joining the largest group.
+fn classify(ranks: &[Rank], rules: &RuleSet) -> HandType {
+    let mut counts = [0; 15];
+    let mut wildcards = 0;
+    for &rank in ranks {
+        if rules.is_wildcard(rank) {
+            wildcards += 1;
         } else {
-            return match hand_type {
-                HandType::FiveOfAKind(rank) => HandType::FiveOfAKind(rank),
-                HandType::FourOfAKind(rank) => {
-                    if rank == Rank::Jack {
-                        HandType::FiveOfAKind(highest_card)
-                    } else {
-                        HandType::FiveOfAKind(rank)
-                    }
-                },
-                HandType::FullHouse(big_rank, small_rank) => {
-                    if big_rank == Rank::Jack {
-                        HandType::FiveOfAKind(small_rank)
-                    } else if small_rank == Rank::Jack {
-                        HandType::FiveOfAKind(big_rank)
-                    } else {
-                        if joker_count >= 2 {
-                            HandType::FiveOfAKind(big_rank)
-                        } else {
-                            HandType::FourOfAKind(big_rank)
-                        }
-                    }
-                },
-                HandType::ThreeOfAKind(rank) => {
-                    if rank == Rank::Jack {
-                        HandType::FourOfAKind(rank)
-                    } else {
-                        HandType::FourOfAKind(rank)
-                    }
-                },
-                HandType::TwoPair(big_rank, small_rank) => {
-                    if big_rank == Rank::Jack {
-                        HandType::FourOfAKind(small_rank)
-                    } else if small_rank == Rank::Jack {
-                        HandType::FourOfAKind(big_rank)
-                    } else {
-                        HandType::FullHouse(big_rank, small_rank)
-                    }
-                },
-                HandType::OnePair(rank) => {
-                    if rank == Rank::Jack {
-                        HandType::ThreeOfAKind(highest_card)
-                    } else {
-                        HandType::ThreeOfAKind(rank)
-                    }
-                },
-                HandType::HighCard(rank) => {
-                    if rank == Rank::Jack {
-                        HandType::OnePair(highest_card)
-                    } else {
-                        HandType::OnePair(rank)
-                    }
-                },
-            }
+            counts[rank as usize] += 1;
         }
     }
+
+    // Groups of matching cards, largest first and higher ranks first among equals
+    let mut groups: Vec<(usize, Rank)> = (2..15)
+        .filter(|&rank| counts[rank] > 0)
+        .map(|rank| (counts[rank], Rank::from(rank)))
+        .collect();
+    groups.sort_by(|a, b| b.cmp(a));
+
+    match groups.first_mut() {
+        Some(largest) => largest.0 += wildcards,
+        // Nothing but wildcards, which stay as themselves
+        None => groups.push((wildcards, *ranks.iter().max().expect("Hand has no cards"))),
+    }
+
+    let (size, rank) = groups[0];
+    match (size, groups.get(1)) {
+        (5.., _) => HandType::FiveOfAKind(rank),
+        (4, _) => HandType::FourOfAKind(rank),
+        (3, Some(&(2.., second))) => HandType::FullHouse(rank, second),
+        (3, _) => HandType::ThreeOfAKind(rank),
+        (2, Some(&(2, second))) => HandType::TwoPair(rank, second),
+        (2, _) => HandType::OnePair(rank),
+        _ => HandType::HighCard(rank),
+    }
 }
 
 
@@ -335,18 +328,27 @@ impl From<std::io::Error> for ParseError {
     }
 }
 
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::IoError(error) => write!(f, "{}", error),
+            ParseError::OtherError(error) => write!(f, "{}", error),
+        }
+    }
+}
+
 impl From<&'static str> for ParseError {
     fn from(error: &'static str) -> Self {
         ParseError::OtherError(error)
     }
 }
 
-fn parse_hands<R: BufRead>(reader: R) -> Result<Vec<Hand>, ParseError> {
+fn parse_hands<R: BufRead>(reader: R, rules: &RuleSet) -> Result<Vec<Hand>, ParseError> {
     let mut hands = Vec::new();
 
     for line in reader.lines() {
         let line = line?;
-        let hand = Hand::try_from(line)?;
+        let hand = Hand::parse(&line, rules.clone())?;
         hands.push(hand);
     }
 
@@ -356,37 +358,141 @@ fn parse_hands<R: BufRead>(reader: R) -> Result<Vec<Hand>, ParseError> {
 }
 
 fn main() {
-    // Get file name from command line
+    // Get file name (or "equity" and a partial hand) and options from command line
     let args: Vec<String> = env::args().collect();
-    let filename = args.get(1).expect("Please provide a filename");
+    let mut filename = None;
+    let mut wildcards = None;
+    let mut poker = false;
+    let mut partial_hand = None;
+    let mut against = None;
+    let mut trials = 100_000;
+    let mut seed = 2023;
+    let mut format = "table".to_string();
+    let mut sort = SortKey::Rank;
+    let mut type_filter = None;
+    let mut show_ties = false;
+
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--wildcards" => {
+                let ranks = args.next().expect("Please provide wildcard ranks after --wildcards");
+                wildcards = Some(ranks.parse::<RuleSet>().unwrap_or_else(|e| panic!("{}: {}", e, ranks)));
+            },
+            "--poker" => poker = true,
+            "--against" => against = Some(args.next().expect("Please provide a hand after --against")),
+            "--trials" => trials = args.next().expect("Please provide a count after --trials").parse().expect("Failed to parse trials"),
+            "--seed" => seed = args.next().expect("Please provide a seed after --seed").parse().expect("Failed to parse seed"),
+            "--format" => format = args.next().expect("Please provide table, csv or json after --format").clone(),
+            "--sort" => {
+                let key = args.next().expect("Please provide a sort key after --sort");
+                sort = SortKey::try_from(key.as_str()).unwrap_or_else(|e| panic!("{}", e));
+            },
+            "--type" => {
+                let name = args.next().expect("Please provide a hand type after --type");
+                type_filter = Some(HandType::try_from(name.as_str()).unwrap_or_else(|e| panic!("{}", e)));
+            },
+            "--ties" => show_ties = true,
+            _ if filename.is_some_and(|command: &String| command == "equity") => partial_hand = Some(arg),
+            _ => filename = Some(arg),
+        }
+    }
+    let filename = filename.expect("Please provide a filename or 'equity <hand>'");
+
+    if filename == "equity" {
+        let parse = |hand: &str| equity::PartialHand::try_from(hand).unwrap_or_else(|e| panic!("{}: {}", e, hand));
+        let hand = parse(partial_hand.expect("Please provide a hand like KK?J? after equity"));
+        if trials == 0 {
+            eprintln!("Please provide at least one trial after --trials");
+            std::process::exit(1);
+        }
+        // Equity is about the joker game unless other wildcards are given
+        let equity_rules = wildcards.unwrap_or(RuleSet::Jokers);
 
-    let file = File::open(filename).expect("Failed to open file");
-    let reader = BufReader::new(file);
+        print!("{}", equity::type_odds(&hand, &equity_rules, trials, seed));
+        if let Some(against) = against {
+            println!("Against {}: {}", against, equity::matchup(&hand, &parse(against), &equity_rules, trials, seed));
+        }
+        return;
+    }
 
-    let hands = parse_hands(reader).expect("Can't parse hands");
+    if poker {
+        let file = File::open(filename).expect("Failed to open file");
+        let hands = poker::parse_poker_hands(BufReader::new(file)).unwrap_or_else(|e| panic!("Can't parse poker hands: {}", e));
 
-    let mut rank = 1;
+        let mut total_winnings: u32 = 0;
+        for (rank, hand) in (1..).zip(&hands) {
+            let winnings = hand.bid.checked_mul(rank).expect("Winnings overflow");
+            total_winnings = total_winnings.checked_add(winnings).expect("Total winnings overflow");
 
-    let mut total_winnings = 0;
+            let poker_rank = hand.rank();
+            println!("{} {:?} {:?} bid: {} winnings: {}", rank, poker_rank.hand_type, poker_rank.kickers, hand.bid, winnings);
+        }
+        println!("Total poker winnings: {}", total_winnings);
+        return;
+    }
 
-    for hand in hands {
-        let winnings = hand.bid * rank as u32;
+    let mut rule_sets = vec![("standard rules", RuleSet::Standard), ("jokers", RuleSet::Jokers)];
+    if let Some(wildcards) = wildcards {
+        rule_sets.push(("custom wildcards", wildcards));
+    }
 
-        total_winnings += winnings;
+    if !["table", "csv", "json"].contains(&format.as_str()) {
+        panic!("Unknown format '{}', expected table, csv or json", format);
+    }
 
-        println!(
-            "{} {} {:?} bid: {} winnings: {}",
-            rank,
-            hand.cards.iter().map(|c| format!("{:?}", c)).collect::<String>(),
-            hand.hand_type(),
-            hand.bid,
-            winnings
-        );
+    let mut totals = Vec::new();
+    let mut results = Vec::new();
+    for (name, rules) in rule_sets {
+        let file = File::open(filename).expect("Failed to open file");
+        let reader = BufReader::new(file);
+
+        let hands = parse_hands(reader, &rules).unwrap_or_else(|e| panic!("Can't parse hands: {}", e));
+
+        let mut standings = Standings::from_hands(&hands).unwrap_or_else(|e| {
+            eprintln!("With {}: {}", name, e);
+            std::process::exit(1);
+        });
+        totals.push((name, standings.total_winnings()));
+
+        if let Some(hand_type) = type_filter {
+            standings = standings.filter_type(hand_type);
+        }
+        standings.sort_by(sort);
+        results.push((name, standings));
+    }
 
-        rank += 1;
+    if format == "table" {
+        for (name, standings) in &results {
+            println!("With {}:", name);
+            println!("{}", standings);
+            if show_ties {
+                for group in &standings.ties {
+                    println!("Tied ranks: {:?}", group);
+                }
+            }
+        }
+        for (name, total) in totals {
+            println!("Total winnings with {}: {}", name, total);
+        }
+        return;
     }
 
-    println!("Total winnings: {}", total_winnings);
+    // Keep stdout a single document, and everything else on stderr
+    match format.as_str() {
+        "csv" => print!("{}", standings::to_csv(&results)),
+        _ => print!("{}", standings::to_json(&results)),
+    }
+    if show_ties && format == "csv" {
+        for (name, standings) in &results {
+            for group in &standings.ties {
+                eprintln!("Tied ranks with {}: {:?}", name, group);
+            }
+        }
+    }
+    for (name, total) in totals {
+        eprintln!("Total winnings with {}: {}", name, total);
+    }
 }
 
 #[cfg(test)]
@@ -445,7 +551,7 @@ AKQJT 0
 
         let expected_hands = vec![
             Hand {
-                cards: [
+                cards: vec![
                     Card { rank: Rank::Nine },
                     Card { rank: Rank::Eight },
                     Card { rank: Rank::Seven },
@@ -453,9 +559,10 @@ AKQJT 0
                     Card { rank: Rank::Five },
                 ],
                 bid: 999,
+                rules: RuleSet::Jokers,
             },
             Hand {
-                cards: [
+                cards: vec![
                     Card { rank: Rank::Three },
                     Card { rank: Rank::Two },
                     Card { rank: Rank::Ten },
@@ -463,9 +570,10 @@ AKQJT 0
                     Card { rank: Rank::King },
                 ],
                 bid: 765,
+                rules: RuleSet::Jokers,
             },
             Hand {
-                cards: [
+                cards: vec![
                     Card { rank: Rank::Ace },
                     Card { rank: Rank::King },
                     Card { rank: Rank::Queen },
@@ -473,10 +581,11 @@ AKQJT 0
                     Card { rank: Rank::Ten },
                 ],
                 bid: 0,
+                rules: RuleSet::Jokers,
             },
         ];
 
-        match parse_hands(reader) {
+        match parse_hands(reader, &RuleSet::Jokers) {
             Ok(hands) => assert_eq!(hands, expected_hands),
             Err(e) => panic!("Error: {:?}", e),
         }
@@ -520,7 +629,7 @@ QQQJA 483"
             .to_string();
         let reader = Cursor::new(input);
 
-        let hands = parse_hands(reader).unwrap();
+        let hands = parse_hands(reader, &RuleSet::Jokers).unwrap();
 
         assert_eq!(hands[0].hand_type(), HandType::OnePair(Rank::Three));
         assert_eq!(
@@ -544,11 +653,193 @@ QQQJA 483"
             .to_string();
         let reader = Cursor::new(input);
 
-        let hands = parse_hands(reader).unwrap();
+        let hands = parse_hands(reader, &RuleSet::Jokers).unwrap();
 
         assert_eq!(hands[0].cards.iter().map(|c| format!("{:?}", c)).collect::<String>(), "65432");
         assert_eq!(hands[1].cards.iter().map(|c| format!("{:?}", c)).collect::<String>(), "72654");
         assert_eq!(hands[2].cards.iter().map(|c| format!("{:?}", c)).collect::<String>(), "74568");
         assert_eq!(hands[3].cards.iter().map(|c| format!("{:?}", c)).collect::<String>(), "76543");
     }
+
+    #[test]
+    fn test_rule_sets() {
+        let input = "32T3K 765
+T55J5 684
+KK677 28
+KTJJT 220
+QQQJA 483";
+
+        let total = |rules: RuleSet| {
+            parse_hands(Cursor::new(input), &rules).unwrap()
+                .iter()
+                .zip(1..)
+                .map(|(hand, rank)| hand.bid * rank)
+                .sum::<u32>()
+        };
+
+        assert_eq!(total(RuleSet::Standard), 6440);
+        assert_eq!(total(RuleSet::Jokers), 5905);
+        assert_eq!(total("J".parse().unwrap()), 5905);
+
+        let hand = Hand::parse("QQQJA 483", RuleSet::Standard).unwrap();
+        assert_eq!(hand.hand_type(), HandType::ThreeOfAKind(Rank::Queen));
+        assert_eq!(hand.hand_type_with(&RuleSet::Jokers), HandType::FourOfAKind(Rank::Queen));
+        assert_eq!(hand.hand_type_with(&"A".parse().unwrap()), HandType::FourOfAKind(Rank::Queen));
+    }
+
+    #[test]
+    fn test_cmp_with() {
+        let jacks = Hand::parse("JJJ23 1", RuleSet::Standard).unwrap();
+        let tens = Hand::parse("TTT23 1", RuleSet::Standard).unwrap();
+
+        assert!(jacks > tens);
+        assert_eq!(jacks.cmp_with(&tens, &RuleSet::Jokers), std::cmp::Ordering::Greater);
+        // Both are four of a kind with wildcard 2s, and J beats T
+        assert_eq!(jacks.cmp_with(&tens, &RuleSet::Wildcards(vec![Rank::Two])), std::cmp::Ordering::Greater);
+        // With J and T wild both are five of a kind, and the wildcards tie
+        assert_eq!(jacks.cmp_with(&tens, &RuleSet::Wildcards(vec![Rank::Jack, Rank::Ten, Rank::Three, Rank::Two])), std::cmp::Ordering::Equal);
+        assert!("X".parse::<RuleSet>().is_err());
+    }
+
+    #[test]
+    fn test_hand_type_names() {
+        assert_eq!(HandType::try_from("FullHouse"), Ok(HandType::FullHouse(Rank::Ace, Rank::Ace)));
+        assert_eq!(
+            HandType::try_from("Flush"),
+            Err("Unknown hand type 'Flush', expected FiveOfAKind, FourOfAKind, FullHouse, ThreeOfAKind, TwoPair, OnePair, HighCard".to_string())
+        );
+    }
+
+    #[test]
+    fn test_cmp_different_rules() {
+        let parse = |line: &str, rules: RuleSet| Hand::parse(line, rules).unwrap();
+        let mut hands = [
+            parse("TTT23 1", RuleSet::Jokers),
+            parse("JJJ23 2", RuleSet::Standard),
+            parse("22345 3", RuleSet::Jokers),
+            parse("KK234 4", RuleSet::Standard),
+        ];
+
+        // Sorting mixed rules groups the hands by rules, then ranks each group
+        hands.sort();
+        let bids: Vec<u32> = hands.iter().map(|hand| hand.bid).collect();
+        assert_eq!(bids, vec![4, 2, 3, 1]);
+
+        // cmp_with ranks them all under the same rules
+        assert_eq!(hands[1].cmp_with(&hands[3], &RuleSet::Jokers), std::cmp::Ordering::Greater);
+        assert_eq!(hands[1].cmp_with(&hands[3], &RuleSet::Standard), std::cmp::Ordering::Greater);
+        assert_eq!(hands[0].cmp_with(&hands[2], &RuleSet::Standard), std::cmp::Ordering::Greater);
+    }
+
+    // Kind of a five card hand without wildcards, from the sizes of its
+    // groups of equal ranks, largest first
+    fn reference_type(ranks: &[Rank]) -> HandType {
+        assert_eq!(ranks.len(), 5);
+        let mut counts = [0; 15];
+        for &rank in ranks {
+            counts[rank as usize] += 1;
+        }
+        counts.sort_unstable_by(|a, b| b.cmp(a));
+
+        let any = Rank::Ace;
+        match counts[..5] {
+            [5, 0, 0, 0, 0] => HandType::FiveOfAKind(any),
+            [4, 1, 0, 0, 0] => HandType::FourOfAKind(any),
+            [3, 2, 0, 0, 0] => HandType::FullHouse(any, any),
+            [3, 1, 1, 0, 0] => HandType::ThreeOfAKind(any),
+            [2, 2, 1, 0, 0] => HandType::TwoPair(any, any),
+            [2, 1, 1, 1, 0] => HandType::OnePair(any),
+            _ => HandType::HighCard(any),
+        }
+    }
+
+    // Every multiset of `size` ranks, each in ascending order
+    fn rank_multisets(size: usize) -> Vec<Vec<Rank>> {
+        let mut multisets = vec![vec![]];
+        for _ in 0..size {
+            multisets = multisets.iter()
+                .flat_map(|ranks: &Vec<Rank>| {
+                    let lowest = ranks.last().map_or(2, |&rank| rank as usize);
+                    (lowest..15).map(move |rank| [ranks.as_slice(), &[Rank::from(rank)]].concat())
+                })
+                .collect();
+        }
+        multisets
+    }
+
+    // Best kind from trying every choice of ranks in place of the wildcards.
+    // Only which ranks are chosen matters, not which wildcard gets which.
+    fn brute_force(ranks: &[Rank], rules: &RuleSet) -> HandType {
+        let (wildcards, others): (Vec<Rank>, Vec<Rank>) = ranks.iter().partition(|&&rank| rules.is_wildcard(rank));
+
+        rank_multisets(wildcards.len()).iter()
+            .map(|choice| reference_type(&[others.as_slice(), choice].concat()))
+            .max()
+            .unwrap()
+    }
+
+    fn rule_sets() -> [RuleSet; 3] {
+        [RuleSet::Standard, RuleSet::Jokers, RuleSet::Wildcards(vec![Rank::Two, Rank::Queen])]
+    }
+
+    #[test]
+    fn test_classify_rank_multisets() {
+        // classify only counts ranks, so each multiset of five ranks stands
+        // for all of its orderings; test_classify_every_hand checks those too
+        let hands = rank_multisets(5);
+        assert_eq!(hands.len(), 6188);
+
+        for rules in rule_sets() {
+            for ranks in &hands {
+                assert_eq!(classify(ranks, &rules).hand(), brute_force(ranks, &rules), "{:?} {:?}", ranks, rules);
+            }
+        }
+    }
+
+    #[test]
+    #[ignore = "takes about a minute in debug builds; run with cargo test --release -- --ignored"]
+    fn test_classify_every_hand() {
+        for rules in rule_sets() {
+            for hand in 0..13usize.pow(5) {
+                let ranks: Vec<Rank> = (0..5).map(|i| Rank::from(2 + hand / 13usize.pow(i) % 13)).collect();
+
+                assert_eq!(classify(&ranks, &rules).hand(), brute_force(&ranks, &rules), "{:?} {:?}", ranks, rules);
+            }
+        }
+    }
+
+    #[test]
+    fn test_reference_type() {
+        let ranks = |cards: &str| cards.chars().map(|c| Rank::try_from(c).unwrap()).collect::<Vec<Rank>>();
+
+        assert_eq!(reference_type(&ranks("32T3K")), HandType::OnePair(Rank::Ace));
+        assert_eq!(reference_type(&ranks("KTJJT")), HandType::TwoPair(Rank::Ace, Rank::Ace));
+        assert_eq!(reference_type(&ranks("T5J55")), HandType::ThreeOfAKind(Rank::Ace));
+        assert_eq!(reference_type(&ranks("2Q2Q2")), HandType::FullHouse(Rank::Ace, Rank::Ace));
+        assert_eq!(brute_force(&ranks("T5J55"), &RuleSet::Jokers), HandType::FourOfAKind(Rank::Ace));
+        assert_eq!(brute_force(&ranks("2Q2Q3"), &RuleSet::Wildcards(vec![Rank::Two, Rank::Queen])), HandType::FiveOfAKind(Rank::Ace));
+    }
+
+    #[test]
+    fn test_classify_other_sizes() {
+        let ranks = |cards: &str| cards.chars().map(|c| Rank::try_from(c).unwrap()).collect::<Vec<Rank>>();
+
+        assert_eq!(classify(&ranks("KK"), &RuleSet::Standard), HandType::OnePair(Rank::King));
+        assert_eq!(classify(&ranks("KJ"), &RuleSet::Jokers), HandType::OnePair(Rank::King));
+        assert_eq!(classify(&ranks("JJJJJJ"), &RuleSet::Jokers), HandType::FiveOfAKind(Rank::Jack));
+        assert_eq!(classify(&ranks("KKQQJ2"), &RuleSet::Jokers), HandType::FullHouse(Rank::King, Rank::Queen));
+        assert_eq!(classify(&ranks("2345678"), &RuleSet::Standard), HandType::HighCard(Rank::Eight));
+        assert_eq!(classify(&ranks("AAA222"), &RuleSet::Wildcards(vec![Rank::Two])), HandType::FiveOfAKind(Rank::Ace));
+
+        // The oddities of the old upgrade table
+        assert_eq!(classify(&ranks("JJJ23"), &RuleSet::Jokers), HandType::FourOfAKind(Rank::Three));
+        assert_eq!(classify(&ranks("2JJ3J"), &RuleSet::Jokers), HandType::FourOfAKind(Rank::Three));
+
+        let hand = Hand::parse("KKJ 5", RuleSet::Jokers).unwrap();
+        assert_eq!(hand.hand_type(), HandType::ThreeOfAKind(Rank::King));
+        assert_eq!(Hand::parse("", RuleSet::Jokers), Err("Hand has no cards"));
+        assert_eq!(Hand::parse(" 5", RuleSet::Jokers), Err("Invalid bid"));
+        assert_eq!(Hand::parse("KKJ 5 6", RuleSet::Jokers), Err("Invalid bid"));
+        assert_eq!(Hand::parse("KKJ  5", RuleSet::Jokers).map(|hand| hand.bid), Ok(5));
+    }
 }
@@ -0,0 +1,278 @@
+use std::cmp::Ordering;
+use std::io::BufRead;
+
+use crate::{classify, HandType, ParseError, Rank, RuleSet};
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, PartialOrd, Ord)]
+pub enum Suit {
+    Clubs,
+    Diamonds,
+    Hearts,
+    Spades,
+}
+
+impl TryFrom<char> for Suit {
+    type Error = &'static str;
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        match value {
+            'c' => Ok(Suit::Clubs),
+            'd' => Ok(Suit::Diamonds),
+            'h' => Ok(Suit::Hearts),
+            's' => Ok(Suit::Spades),
+            _ => Err("Invalid suit"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct PokerCard {
+    pub rank: Rank,
+    pub suit: Suit,
+}
+
+impl TryFrom<&str> for PokerCard {
+    type Error = &'static str;
+
+    // A rank then a suit, like "As" or "Td"
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let mut chars = value.chars();
+        let (Some(rank), Some(suit), None) = (chars.next(), chars.next(), chars.next()) else {
+            return Err("Invalid card");
+        };
+
+        Ok(PokerCard { rank: Rank::try_from(rank)?, suit: Suit::try_from(suit)? })
+    }
+}
+
+// Weakest first, so the derived order ranks them
+#[derive(Copy, Clone, Eq, PartialEq, Debug, PartialOrd, Ord)]
+pub enum PokerHandType {
+    HighCard,
+    OnePair,
+    TwoPair,
+    ThreeOfAKind,
+    Straight,
+    Flush,
+    FullHouse,
+    FourOfAKind,
+    StraightFlush,
+}
+
+// A hand's type then the ranks that break ties between hands of that type
+#[derive(Clone, Eq, PartialEq, Debug, PartialOrd, Ord)]
+pub struct PokerRank {
+    pub hand_type: PokerHandType,
+    pub kickers: Vec<Rank>,
+}
+
+// The best five cards, or all of them if there are five or fewer
+pub fn evaluate(cards: &[PokerCard]) -> PokerRank {
+    if cards.len() <= 5 {
+        return evaluate_five(cards);
+    }
+
+    let mut best: Option<PokerRank> = None;
+    for skipped in combinations(cards.len(), cards.len() - 5) {
+        let five: Vec<PokerCard> = (0..cards.len())
+            .filter(|i| !skipped.contains(i))
+            .map(|i| cards[i])
+            .collect();
+        let rank = evaluate_five(&five);
+        if best.as_ref().is_none_or(|best| rank > *best) {
+            best = Some(rank);
+        }
+    }
+    best.expect("More than five cards always has a combination")
+}
+
+// Every way to choose `choose` of the indexes 0..count
+fn combinations(count: usize, choose: usize) -> Vec<Vec<usize>> {
+    if choose == 0 {
+        return vec![vec![]];
+    }
+    (choose - 1..count)
+        .flat_map(|last| {
+            combinations(last, choose - 1).into_iter().map(move |mut chosen| {
+                chosen.push(last);
+                chosen
+            })
+        })
+        .collect()
+}
+
+fn evaluate_five(cards: &[PokerCard]) -> PokerRank {
+    let ranks: Vec<Rank> = cards.iter().map(|card| card.rank).collect();
+
+    // Group ranks, biggest group first, as Camel Cards counts them
+    let mut groups: Vec<(usize, Rank)> = Vec::new();
+    for &rank in &ranks {
+        match groups.iter_mut().find(|(_, grouped)| *grouped == rank) {
+            Some(group) => group.0 += 1,
+            None => groups.push((1, rank)),
+        }
+    }
+    groups.sort_by(|a, b| b.cmp(a));
+    let kickers: Vec<Rank> = groups.iter().map(|&(_, rank)| rank).collect();
+
+    let flush = cards.len() == 5 && cards.iter().all(|card| card.suit == cards[0].suit);
+    let straight_high = if cards.len() == 5 && groups.len() == 5 {
+        let (high, low) = (kickers[0], kickers[4]);
+        if high as u8 - low as u8 == 4 {
+            Some(high)
+        } else if kickers == [Rank::Ace, Rank::Five, Rank::Four, Rank::Three, Rank::Two] {
+            // The wheel, where the ace plays low
+            Some(Rank::Five)
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+
+    let hand_type = match (classify(&ranks, &RuleSet::Standard), straight_high, flush) {
+        (_, Some(_), true) => PokerHandType::StraightFlush,
+        (HandType::FiveOfAKind(_) | HandType::FourOfAKind(_), _, _) => PokerHandType::FourOfAKind,
+        (HandType::FullHouse(_, _), _, _) => PokerHandType::FullHouse,
+        (_, _, true) => PokerHandType::Flush,
+        (_, Some(_), _) => PokerHandType::Straight,
+        (HandType::ThreeOfAKind(_), _, _) => PokerHandType::ThreeOfAKind,
+        (HandType::TwoPair(_, _), _, _) => PokerHandType::TwoPair,
+        (HandType::OnePair(_), _, _) => PokerHandType::OnePair,
+        (HandType::HighCard(_), _, _) => PokerHandType::HighCard,
+    };
+
+    match straight_high {
+        Some(high) => PokerRank { hand_type, kickers: vec![high] },
+        None => PokerRank { hand_type, kickers },
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct PokerHand {
+    pub cards: Vec<PokerCard>,
+    pub bid: u32,
+}
+
+impl PokerHand {
+    // Cards separated by spaces, then the bid, like "As Kd Qh Jc Ts 100"
+    pub fn parse(value: &str) -> Result<Self, &'static str> {
+        let mut parts: Vec<&str> = value.split_whitespace().collect();
+        let bid = parts.pop().ok_or("Invalid bid")?.parse().or(Err("Invalid bid"))?;
+
+        let cards = parts.into_iter()
+            .map(PokerCard::try_from)
+            .collect::<Result<Vec<PokerCard>, _>>()?;
+        if cards.len() != 5 && cards.len() != 7 {
+            return Err("Poker hands need 5 or 7 cards");
+        }
+        if (1..cards.len()).any(|i| cards[..i].contains(&cards[i])) {
+            return Err("Duplicate card");
+        }
+
+        Ok(PokerHand { cards, bid })
+    }
+
+    pub fn rank(&self) -> PokerRank {
+        evaluate(&self.cards)
+    }
+}
+
+impl PartialOrd for PokerHand {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PokerHand {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank().cmp(&other.rank())
+    }
+}
+
+// Like `parse_hands`, weakest hand first
+pub fn parse_poker_hands<R: BufRead>(reader: R) -> Result<Vec<PokerHand>, ParseError> {
+    let mut hands = Vec::new();
+
+    for line in reader.lines() {
+        let line = line?;
+        hands.push(PokerHand::parse(&line)?);
+    }
+
+    hands.sort();
+
+    Ok(hands)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rank(cards: &str) -> PokerRank {
+        PokerHand::parse(&format!("{} 1", cards)).unwrap().rank()
+    }
+
+    #[test]
+    fn test_parse() {
+        let hand = PokerHand::parse("As Kd Qh Jc Ts 100").unwrap();
+        assert_eq!(hand.cards[0], PokerCard { rank: Rank::Ace, suit: Suit::Spades });
+        assert_eq!(hand.bid, 100);
+
+        assert_eq!(PokerHand::parse("As Kd Qh Jc Tx 1"), Err("Invalid suit"));
+        assert_eq!(PokerHand::parse("As Kd Qh Jc 10s 1"), Err("Invalid card"));
+        assert_eq!(PokerHand::parse("As Kd Qh Jc 1"), Err("Poker hands need 5 or 7 cards"));
+        assert_eq!(PokerHand::parse("As Kd Qh Jc As 1"), Err("Duplicate card"));
+        assert_eq!(PokerHand::parse("As Kd Qh Jc Ts x"), Err("Invalid bid"));
+    }
+
+    #[test]
+    fn test_hand_types() {
+        assert_eq!(rank("As Ks Qs Js Ts").hand_type, PokerHandType::StraightFlush);
+        assert_eq!(rank("9c 9d 9h 9s 2c").hand_type, PokerHandType::FourOfAKind);
+        assert_eq!(rank("9c 9d 9h 2s 2c").hand_type, PokerHandType::FullHouse);
+        assert_eq!(rank("Ah 9h 7h 4h 2h").hand_type, PokerHandType::Flush);
+        assert_eq!(rank("6c 5d 4h 3s 2c").hand_type, PokerHandType::Straight);
+        assert_eq!(rank("9c 9d 9h 3s 2c").hand_type, PokerHandType::ThreeOfAKind);
+        assert_eq!(rank("9c 9d 3h 3s 2c").hand_type, PokerHandType::TwoPair);
+        assert_eq!(rank("9c 9d 4h 3s 2c").hand_type, PokerHandType::OnePair);
+        assert_eq!(rank("Kc 9d 4h 3s 2c").hand_type, PokerHandType::HighCard);
+        // Q K A 2 3 doesn't wrap around
+        assert_eq!(rank("Qc Kd Ah 2s 3c").hand_type, PokerHandType::HighCard);
+    }
+
+    #[test]
+    fn test_wheel() {
+        let wheel = rank("Ac 2d 3h 4s 5c");
+        assert_eq!(wheel, PokerRank { hand_type: PokerHandType::Straight, kickers: vec![Rank::Five] });
+        assert!(wheel < rank("2d 3h 4s 5c 6c"));
+        assert_eq!(rank("Ah 2h 3h 4h 5h").hand_type, PokerHandType::StraightFlush);
+    }
+
+    #[test]
+    fn test_kickers() {
+        assert!(rank("Ac Ad Kh 3s 2c") > rank("Ac Ad Qh Js Tc"));
+        assert!(rank("9c 9d 3h 3s Ac") > rank("9c 9d 3h 3s Kc"));
+        assert!(rank("3c 3d 3h 2s 2c") < rank("4c 4d 4h 2s 2c"));
+        assert_eq!(rank("Ac Ad Kh 3s 2c"), rank("Ah As Kc 3d 2h"));
+    }
+
+    #[test]
+    fn test_best_five_of_seven() {
+        // A flush hidden among seven cards beats the pair
+        assert_eq!(rank("Ah Ad 9h 7h 4h 2h Kc").hand_type, PokerHandType::Flush);
+        assert_eq!(rank("Ah Kd 9h 7c 4s 2h Kc"), PokerRank {
+            hand_type: PokerHandType::OnePair,
+            kickers: vec![Rank::King, Rank::Ace, Rank::Nine, Rank::Seven],
+        });
+        assert_eq!(combinations(7, 2).len(), 21);
+    }
+
+    #[test]
+    fn test_parse_poker_hands() {
+        let input = "Ac 2d 3h 4s 5c 10\nKc Kd 4h 3s 2c 20\n9c 9d 9h 9s 2c 30";
+        let hands = parse_poker_hands(input.as_bytes()).unwrap();
+
+        let bids: Vec<u32> = hands.iter().map(|hand| hand.bid).collect();
+        assert_eq!(bids, vec![20, 10, 30]);
+    }
+}
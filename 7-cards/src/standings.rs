@@ -0,0 +1,259 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::{Hand, HandType};
+
+// One hand's place in the final ranking
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Entry {
+    pub rank: u32,
+    pub cards: String,
+    pub hand_type: HandType,
+    pub bid: u32,
+    pub winnings: u32,
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum SortKey {
+    Rank,
+    // Weakest type first, then by rank within a type
+    Type,
+    Bid,
+    Winnings,
+}
+
+impl TryFrom<&str> for SortKey {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "rank" => Ok(SortKey::Rank),
+            "type" => Ok(SortKey::Type),
+            "bid" => Ok(SortKey::Bid),
+            "winnings" => Ok(SortKey::Winnings),
+            _ => Err(format!("Unknown sort key '{}', expected rank, type, bid or winnings", value)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Standings {
+    pub entries: Vec<Entry>,
+    // Ranks of hands that compare equal, in groups of two or more
+    pub ties: Vec<Vec<u32>>,
+}
+
+impl Standings {
+    // From hands sorted weakest first, as `parse_hands` returns them
+    pub fn from_hands(hands: &[Hand]) -> Result<Standings, String> {
+        let entries = (1..).zip(hands)
+            .map(|(rank, hand)| Ok(Entry {
+                rank,
+                cards: hand.cards.iter().map(|c| format!("{:?}", c)).collect(),
+                hand_type: hand.hand_type(),
+                bid: hand.bid,
+                winnings: hand.bid.checked_mul(rank).ok_or_else(|| format!("Winnings overflow at rank {}", rank))?,
+            }))
+            .collect::<Result<Vec<Entry>, String>>()?;
+        entries.iter().try_fold(0u32, |total, entry| total.checked_add(entry.winnings))
+            .ok_or("Total winnings overflow")?;
+
+        let mut ties: Vec<Vec<u32>> = Vec::new();
+        for (rank, pair) in (2..).zip(hands.windows(2)) {
+            if pair[0].cmp(&pair[1]) != Ordering::Equal {
+                continue;
+            }
+            match ties.last_mut() {
+                Some(group) if group.last() == Some(&(rank - 1)) => group.push(rank),
+                _ => ties.push(vec![rank - 1, rank]),
+            }
+        }
+
+        Ok(Standings { entries, ties })
+    }
+
+    // Can't overflow: from_hands checked the total of every entry
+    pub fn total_winnings(&self) -> u32 {
+        self.entries.iter().map(|entry| entry.winnings).sum()
+    }
+
+    pub fn sort_by(&mut self, key: SortKey) {
+        match key {
+            SortKey::Rank => self.entries.sort_by_key(|entry| entry.rank),
+            SortKey::Type => self.entries.sort_by_key(|entry| (entry.hand_type.hand(), entry.rank)),
+            SortKey::Bid => self.entries.sort_by_key(|entry| (entry.bid, entry.rank)),
+            SortKey::Winnings => self.entries.sort_by_key(|entry| (entry.winnings, entry.rank)),
+        }
+    }
+
+    // Only the entries of one type, compared by `HandType::hand`
+    pub fn filter_type(&self, hand_type: HandType) -> Standings {
+        let entries: Vec<Entry> = self.entries.iter()
+            .filter(|entry| entry.hand_type.hand() == hand_type.hand())
+            .cloned()
+            .collect();
+        let ties = self.ties.iter()
+            .filter(|group| entries.iter().any(|entry| entry.rank == group[0]))
+            .cloned()
+            .collect();
+
+        Standings { entries, ties }
+    }
+
+    fn to_json(&self) -> String {
+        let entries: Vec<String> = self.entries.iter()
+            .map(|entry| format!(
+                "    {{\"rank\": {}, \"cards\": \"{}\", \"type\": \"{}\", \"bid\": {}, \"winnings\": {}}}",
+                entry.rank, entry.cards, entry.hand_type.name(), entry.bid, entry.winnings
+            ))
+            .collect();
+        let ties: Vec<String> = self.ties.iter()
+            .map(|group| format!("[{}]", group.iter().map(|rank| rank.to_string()).collect::<Vec<_>>().join(", ")))
+            .collect();
+        let entries = if entries.is_empty() {
+            "[]".to_string()
+        } else {
+            format!("[\n{}\n  ]", entries.join(",\n"))
+        };
+
+        format!(
+            "{{\n  \"entries\": {},\n  \"ties\": [{}],\n  \"total_winnings\": {}\n}}",
+            entries, ties.join(", "), self.total_winnings()
+        )
+    }
+}
+
+// One CSV document for standings under several rule sets, with a column
+// naming the rules
+pub fn to_csv(rule_sets: &[(&str, Standings)]) -> String {
+    let mut csv = String::from("rules,rank,cards,type,bid,winnings\n");
+    for (rules, standings) in rule_sets {
+        for entry in &standings.entries {
+            csv += &format!(
+                "{},{},{},{},{},{}\n",
+                rules, entry.rank, entry.cards, entry.hand_type.name(), entry.bid, entry.winnings
+            );
+        }
+    }
+    csv
+}
+
+// One JSON object with the standings under each rule set keyed by its name
+pub fn to_json(rule_sets: &[(&str, Standings)]) -> String {
+    let objects: Vec<String> = rule_sets.iter()
+        .map(|(rules, standings)| format!("  \"{}\": {}", rules, standings.to_json().replace('\n', "\n  ")))
+        .collect();
+
+    format!("{{\n{}\n}}\n", objects.join(",\n"))
+}
+
+impl fmt::Display for Standings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self.entries.iter().map(|entry| entry.cards.len()).max().unwrap_or(0).max(5);
+
+        writeln!(f, "{:>5}  {:<width$}  {:<12}  {:>6}  {:>9}", "rank", "cards", "type", "bid", "winnings")?;
+        for entry in &self.entries {
+            writeln!(
+                f, "{:>5}  {:<width$}  {:<12}  {:>6}  {:>9}",
+                entry.rank, entry.cards, entry.hand_type.name(), entry.bid, entry.winnings
+            )?;
+        }
+        write!(f, "Total: {}", self.total_winnings())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_hands, Rank, RuleSet};
+
+    fn standings(input: &str) -> Standings {
+        Standings::from_hands(&parse_hands(input.as_bytes(), &RuleSet::Jokers).unwrap()).unwrap()
+    }
+
+    const EXAMPLE: &str = "32T3K 765
+T55J5 684
+KK677 28
+KTJJT 220
+QQQJA 483";
+
+    #[test]
+    fn test_from_hands() {
+        let standings = standings(EXAMPLE);
+
+        assert_eq!(standings.total_winnings(), 5905);
+        assert_eq!(standings.entries[0], Entry {
+            rank: 1,
+            cards: "32T3K".to_string(),
+            hand_type: HandType::OnePair(Rank::Three),
+            bid: 765,
+            winnings: 765,
+        });
+        assert_eq!(standings.entries[4].cards, "KTJJT");
+        assert!(standings.ties.is_empty());
+    }
+
+    #[test]
+    fn test_sort_and_filter() {
+        let mut standings = standings(EXAMPLE);
+
+        let fours = standings.filter_type(HandType::FourOfAKind(Rank::Two));
+        let cards: Vec<&str> = fours.entries.iter().map(|entry| entry.cards.as_str()).collect();
+        assert_eq!(cards, vec!["T55J5", "QQQJA", "KTJJT"]);
+        assert_eq!(fours.total_winnings(), 684 * 3 + 483 * 4 + 220 * 5);
+
+        standings.sort_by(SortKey::Bid);
+        assert_eq!(standings.entries[0].bid, 28);
+        standings.sort_by(SortKey::Type);
+        assert_eq!(standings.entries.iter().map(|entry| entry.rank).collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+        assert!(SortKey::try_from("colour").is_err());
+    }
+
+    #[test]
+    fn test_ties() {
+        let standings = standings("KK677 1\n23456 2\nKK677 3\nKK677 4\n23456 5");
+
+        assert_eq!(standings.ties, vec![vec![1, 2], vec![3, 4, 5]]);
+        assert_eq!(standings.filter_type(HandType::TwoPair(Rank::Two, Rank::Two)).ties, vec![vec![3, 4, 5]]);
+    }
+
+    #[test]
+    fn test_winnings_overflow() {
+        assert_eq!(
+            Standings::from_hands(&parse_hands("23456 1\n34567 4294967295".as_bytes(), &RuleSet::Jokers).unwrap()),
+            Err("Winnings overflow at rank 2".to_string())
+        );
+        assert_eq!(
+            Standings::from_hands(&parse_hands("23456 4294967295\n34567 1".as_bytes(), &RuleSet::Jokers).unwrap()),
+            Err("Total winnings overflow".to_string())
+        );
+    }
+
+    #[test]
+    fn test_exports() {
+        let standings = standings("32T3K 765\nKK677 28");
+        let rule_sets = [("jokers", standings.clone()), ("fours", standings.filter_type(HandType::FourOfAKind(Rank::Two)))];
+
+        assert_eq!(to_csv(&rule_sets[..1]), "rules,rank,cards,type,bid,winnings\njokers,1,32T3K,OnePair,765,765\njokers,2,KK677,TwoPair,28,56\n");
+        assert_eq!(to_json(&rule_sets), r#"{
+  "jokers": {
+    "entries": [
+      {"rank": 1, "cards": "32T3K", "type": "OnePair", "bid": 765, "winnings": 765},
+      {"rank": 2, "cards": "KK677", "type": "TwoPair", "bid": 28, "winnings": 56}
+    ],
+    "ties": [],
+    "total_winnings": 821
+  },
+  "fours": {
+    "entries": [],
+    "ties": [],
+    "total_winnings": 0
+  }
+}
+"#);
+        assert_eq!(standings.to_string(), " rank  cards  type             bid   winnings
+    1  32T3K  OnePair          765        765
+    2  KK677  TwoPair           28         56
+Total: 821");
+    }
+}